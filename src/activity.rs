@@ -0,0 +1,375 @@
+use std::fmt;
+use std::str::FromStr;
+
+use gpx::{Gpx, Waypoint};
+use phf::phf_map;
+use serde::{Deserialize, Serialize};
+use time::{OffsetDateTime, Duration};
+
+use crate::error::Error;
+use crate::stat::{points_distance, ways_distance, ways_elevations, ElevationFilter, Smoothing, SpeedFilter};
+
+
+/// Тип активности
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Activity {
+    Walking,
+    Hiking,
+    Running,
+    Cycling,
+    MountainBiking,
+    Skiing,
+    Driving,
+    Swimming,
+}
+
+/// Настройки подсчета статистики, зависящие от типа активности
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Profile {
+    pub pause_duration: Duration, // Минимальная продолжительность паузы
+    pub pause_distance: f64, // Максимальное расстояние в метрах, пройденное за время паузы
+    pub max_speed: f64, // Правдоподобная максимальная скорость, метров/час
+    pub speed_filter: SpeedFilter, // Фильтрация GPS-шума при подсчете скорости
+    pub elevation_filter: ElevationFilter, // Фильтрация шума высоты при подсчете подъема
+    pub pace: bool, // Выводить темп вместо скорости
+}
+
+// Фактически pause_distance / pause_duration - это скорость, ниже
+// которой движение между двумя gps-показаниями считается паузой
+static PROFILES: phf::Map<&'static str, Profile> = phf_map! {
+    "walking" => Profile {
+        pause_duration: Duration::minutes(2),
+        pause_distance: 1000.0 * (2.0 / 60.0),
+        max_speed: 12_000.0,
+        speed_filter: SpeedFilter {
+            min_distance: 10.0,
+            min_time: 10.0,
+            smoothing: Smoothing::Median(5),
+        },
+        elevation_filter: ElevationFilter {
+            window: 5,
+            threshold: 3.0,
+        },
+        pace: true,
+    },
+    "hiking" => Profile {
+        pause_duration: Duration::minutes(3),
+        pause_distance: 1000.0 * (3.0 / 60.0),
+        max_speed: 15_000.0,
+        speed_filter: SpeedFilter {
+            min_distance: 10.0,
+            min_time: 15.0,
+            smoothing: Smoothing::Median(5),
+        },
+        elevation_filter: ElevationFilter {
+            window: 5,
+            threshold: 3.0,
+        },
+        pace: true,
+    },
+    "running" => Profile {
+        pause_duration: Duration::minutes(1),
+        pause_distance: 3000.0 * (1.0 / 60.0),
+        max_speed: 30_000.0,
+        speed_filter: SpeedFilter {
+            min_distance: 10.0,
+            min_time: 5.0,
+            smoothing: Smoothing::Median(5),
+        },
+        elevation_filter: ElevationFilter {
+            window: 5,
+            threshold: 3.0,
+        },
+        pace: true,
+    },
+    "cycling" => Profile {
+        pause_duration: Duration::minutes(2),
+        pause_distance: 5000.0 * (2.0 / 60.0),
+        max_speed: 100_000.0,
+        speed_filter: SpeedFilter {
+            min_distance: 20.0,
+            min_time: 5.0,
+            smoothing: Smoothing::Median(5),
+        },
+        elevation_filter: ElevationFilter {
+            window: 5,
+            threshold: 3.0,
+        },
+        pace: false,
+    },
+    "mountainbiking" => Profile {
+        pause_duration: Duration::minutes(2),
+        pause_distance: 3000.0 * (2.0 / 60.0),
+        max_speed: 80_000.0,
+        speed_filter: SpeedFilter {
+            min_distance: 15.0,
+            min_time: 5.0,
+            smoothing: Smoothing::Median(5),
+        },
+        elevation_filter: ElevationFilter {
+            window: 5,
+            threshold: 3.0,
+        },
+        pace: false,
+    },
+    "skiing" => Profile {
+        pause_duration: Duration::minutes(2),
+        pause_distance: 3000.0 * (2.0 / 60.0),
+        max_speed: 130_000.0,
+        speed_filter: SpeedFilter {
+            min_distance: 20.0,
+            min_time: 3.0,
+            smoothing: Smoothing::Kalman { process: 4.0, measurement: 9.0 },
+        },
+        elevation_filter: ElevationFilter {
+            window: 5,
+            threshold: 4.0,
+        },
+        pace: false,
+    },
+    "driving" => Profile {
+        pause_duration: Duration::minutes(3),
+        pause_distance: 5000.0 * (3.0 / 60.0),
+        max_speed: 250_000.0,
+        speed_filter: SpeedFilter {
+            min_distance: 50.0,
+            min_time: 3.0,
+            smoothing: Smoothing::Kalman { process: 9.0, measurement: 25.0 },
+        },
+        elevation_filter: ElevationFilter {
+            window: 7,
+            threshold: 5.0,
+        },
+        pace: false,
+    },
+    "swimming" => Profile {
+        pause_duration: Duration::minutes(1),
+        pause_distance: 500.0 * (1.0 / 60.0),
+        max_speed: 8_000.0,
+        speed_filter: SpeedFilter {
+            min_distance: 10.0,
+            min_time: 20.0,
+            smoothing: Smoothing::Median(7),
+        },
+        elevation_filter: ElevationFilter {
+            window: 1,
+            threshold: 1.0,
+        },
+        pace: false,
+    },
+};
+
+// Названия, которыми разные устройства и сервисы обозначают
+// активность в элементе <type> трека. Strava использует числовые коды
+static GPX_TYPES: phf::Map<&'static str, Activity> = phf_map! {
+    "walking" => Activity::Walking,
+    "walk" => Activity::Walking,
+    "10" => Activity::Walking,
+    "hiking" => Activity::Hiking,
+    "hike" => Activity::Hiking,
+    "4" => Activity::Hiking,
+    "running" => Activity::Running,
+    "run" => Activity::Running,
+    "trail_running" => Activity::Running,
+    "9" => Activity::Running,
+    "cycling" => Activity::Cycling,
+    "biking" => Activity::Cycling,
+    "ride" => Activity::Cycling,
+    "road_biking" => Activity::Cycling,
+    "1" => Activity::Cycling,
+    "mountainbiking" => Activity::MountainBiking,
+    "mountain_biking" => Activity::MountainBiking,
+    "mtb" => Activity::MountainBiking,
+    "skiing" => Activity::Skiing,
+    "alpine_skiing" => Activity::Skiing,
+    "backcountry_skiing" => Activity::Skiing,
+    "cross_country_skiing" => Activity::Skiing,
+    "driving" => Activity::Driving,
+    "motorcycling" => Activity::Driving,
+    "swimming" => Activity::Swimming,
+    "open_water_swimming" => Activity::Swimming,
+    "swim" => Activity::Swimming,
+};
+
+impl Activity {
+    /// Настройки подсчета статистики для данного типа активности
+    pub fn profile(&self) -> &'static Profile {
+        PROFILES.get(self.to_string().to_lowercase().as_str()).unwrap()
+    }
+}
+
+impl fmt::Display for Activity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl FromStr for Activity {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key = s.trim().to_lowercase().replace([' ', '-'], "_");
+
+        GPX_TYPES.get(key.as_str())
+            .copied()
+            .ok_or(Error::UnknownActivity(s.to_string()))
+    }
+}
+
+/// Тип активности, указанный в первом треке GPX-файла, где он задан
+pub fn from_gpx_type(gpx: &Gpx) -> Option<Activity> {
+    gpx.tracks.iter()
+        .filter_map(|track| track.type_.as_deref())
+        .find_map(|type_| type_.parse().ok())
+}
+
+// Скорости движения между соседними gps-показаниями в метрах/час.
+// Отрезки, на которых скорость ниже скорости пешехода, считаются
+// остановками и не учитываются
+fn moving_speeds(ways: &[&[Waypoint]]) -> Vec<f64> {
+    let mut speeds: Vec<f64> = vec!();
+
+    for way in ways {
+        for (p1, p2) in way.iter().zip(way.iter().skip(1)) {
+            if let (Some(t1), Some(t2)) = (p1.time, p2.time) {
+                let t1: OffsetDateTime = t1.into();
+                let t2: OffsetDateTime = t2.into();
+                let seconds = (t2 - t1).abs().as_seconds_f64();
+                if seconds <= 0.0 {
+                    continue;
+                }
+
+                let speed = points_distance(p1, p2) / (seconds / 3600.0);
+                if speed >= 1000.0 {
+                    speeds.push(speed);
+                }
+            }
+        }
+    }
+
+    speeds
+}
+
+/// Медианный каденс трека по сырому содержимому GPX-файла: шагов или оборотов
+/// педалей в минуту из элементов cad расширения Garmin TrackPointExtension.
+/// Библиотека gpx отбрасывает <extensions>, поэтому элементы ищутся в тексте
+/// файла напрямую. Нулевой каденс на остановках и накатом не учитывается
+pub fn cadence(data: &[u8]) -> Option<f64> {
+    let text = String::from_utf8_lossy(data);
+    let mut values: Vec<f64> = text.split('<')
+        .filter_map(|tag| {
+            let (name, value) = tag.split_once('>')?;
+            let local = name.rsplit(':').next()?;
+            if local == "cad" { value.trim().parse().ok() } else { None }
+        })
+        .filter(|value: &f64| *value > 0.0)
+        .collect();
+    if values.len() < 10 {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+
+    Some(values[values.len() / 2])
+}
+
+// Метров пути за один цикл каденса, ниже которых на одинаковой скорости
+// это бег, а не велосипед. Garmin пишет каденс бега как шаги одной ноги
+// в минуту, это 2-3 метра за цикл, а оборот педалей даже на легкой
+// передаче - больше 3 метров
+const RUNNING_STRIDE: f64 = 3.0;
+
+/// Определение типа активности по распределению скоростей и набору высоты.
+/// Медианная скорость движения отделяет ходьбу, бег, велосипед и транспорт,
+/// а подъем на километр пути - горные разновидности активностей.
+/// Каденс, если он известен, различает бег и велосипед на одинаковой
+/// скорости по длине пути за один шаг или оборот педалей.
+/// Лыжи и плавание по скорости надежно не определить, поэтому они
+/// распознаются только по элементу <type>
+pub fn detect(ways: &[&[Waypoint]], cadence: Option<f64>) -> Option<Activity> {
+    let mut speeds = moving_speeds(ways);
+    if speeds.len() < 10 {
+        return None;
+    }
+    speeds.sort_by(|a, b| a.total_cmp(b));
+
+    let median = speeds[speeds.len() / 2];
+    let distance = ways_distance(ways);
+    let climb_rate = match ways_elevations(ways, &ElevationFilter::default()) {
+        Some((total, _, _)) if distance > 0.0 => total / (distance / 1000.0),
+        _ => 0.0,
+    };
+    let cycling = if climb_rate > 25.0 { Activity::MountainBiking } else { Activity::Cycling };
+
+    let activity = match (median, cadence) {
+        (speed, _) if speed < 7_000.0 => {
+            if climb_rate > 40.0 { Activity::Hiking } else { Activity::Walking }
+        },
+        (speed, Some(cadence)) if speed < 20_000.0 => {
+            if speed / 60.0 / cadence < RUNNING_STRIDE { Activity::Running } else { cycling }
+        },
+        (speed, _) if speed < 15_000.0 => Activity::Running,
+        (speed, _) if speed < 20_000.0 && climb_rate > 25.0 => Activity::MountainBiking,
+        (speed, _) if speed < 45_000.0 => Activity::Cycling,
+        _ => Activity::Driving,
+    };
+
+    Some(activity)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Градусов долготы на метр вдоль экватора
+    const DEGREES: f64 = 1.0 / 111_319.490_793_273_57;
+
+    // Ровный путь вдоль экватора: точка каждые 10 секунд при скорости в метрах/час
+    fn steady_way(speed: f64) -> Vec<Waypoint> {
+        (0..60).map(|num| {
+            let mut p = Waypoint::new(geo_types::Point::new(num as f64 * speed / 360.0 * DEGREES, 0.0));
+            p.elevation = Some(100.0);
+            p.time = Some(OffsetDateTime::from_unix_timestamp(1_700_000_000 + num * 10).unwrap().into());
+            p
+        }).collect()
+    }
+
+    fn trkpts(cadences: &[u32]) -> String {
+        cadences.iter()
+            .map(|cad| format!(
+                "<trkpt lat=\"0\" lon=\"0\"><extensions><gpxtpx:TrackPointExtension>\
+                 <gpxtpx:cad>{cad}</gpxtpx:cad></gpxtpx:TrackPointExtension></extensions></trkpt>\n"))
+            .collect()
+    }
+
+    #[test]
+    fn cadence_from_extensions() {
+        let data = trkpts(&[0, 0, 85, 86, 87, 88, 88, 88, 89, 90, 91, 92, 0]);
+        assert_eq!(cadence(data.as_bytes()), Some(88.0));
+
+        let data = "<trkpt><extensions><ns3:TrackPointExtension><ns3:cad> 70 </ns3:cad>\
+                    </ns3:TrackPointExtension></extensions></trkpt>".repeat(10);
+        assert_eq!(cadence(data.as_bytes()), Some(70.0));
+
+        assert_eq!(cadence(trkpts(&[90, 90, 90]).as_bytes()), None);
+        assert_eq!(cadence(b"<gpx><trk><name>cadence</name></trk></gpx>"), None);
+    }
+
+    #[test]
+    fn cadence_separates_running_and_cycling() {
+        // 12 км/ч: без каденса - бег, 200 метров в минуту за 88 шагов - тоже бег,
+        // а за 60 оборотов педалей - велосипед
+        let way = steady_way(12_000.0);
+        let ways: Vec<&[Waypoint]> = vec!(&way);
+        assert_eq!(detect(&ways, None), Some(Activity::Running));
+        assert_eq!(detect(&ways, Some(88.0)), Some(Activity::Running));
+        assert_eq!(detect(&ways, Some(60.0)), Some(Activity::Cycling));
+
+        // 17 км/ч: без каденса - велосипед, при 95 шагах в минуту - бег
+        let way = steady_way(17_000.0);
+        let ways: Vec<&[Waypoint]> = vec!(&way);
+        assert_eq!(detect(&ways, None), Some(Activity::Cycling));
+        assert_eq!(detect(&ways, Some(95.0)), Some(Activity::Running));
+    }
+}
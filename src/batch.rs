@@ -0,0 +1,168 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+
+use crate::error::Error;
+
+
+// Символы, по которым аргумент распознается как шаблон имени файла
+const GLOB_CHARS: [char; 3] = ['*', '?', '['];
+
+fn is_gpx(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gpx"))
+}
+
+// GPX-файлы каталога и всех вложенных каталогов в алфавитном порядке
+fn walk(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<PathBuf>, _>>()?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            walk(&path, paths)?;
+        } else if is_gpx(&path) {
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Пути к GPX-файлам по списку файлов, каталогов и шаблонов вида "rides/**/*.gpx".
+/// Каталоги обходятся рекурсивно. Из каталогов и совпадений с шаблонами
+/// берутся только файлы с расширением gpx. Явно указанные файлы берутся как есть, даже если их нет, - ошибка их чтения
+/// попадет в отчет об ошибках вместе с остальными. Повторы отбрасываются
+pub fn expand(inputs: &[String]) -> Result<Vec<PathBuf>, Error> {
+    let mut paths: Vec<PathBuf> = vec!();
+
+    for input in inputs {
+        let path = Path::new(input);
+
+        if path.is_dir() {
+            walk(path, &mut paths)?;
+        } else if !path.exists() && input.contains(GLOB_CHARS) {
+            let matches = glob::glob(input).map_err(|err| Error::InvalidPattern(format!("{}: {}", input, err.msg)))?;
+
+            for entry in matches {
+                let entry = entry.map_err(|err| Error::Io(err.into()))?;
+                if entry.is_dir() {
+                    walk(&entry, &mut paths)?;
+                } else if is_gpx(&entry) {
+                    paths.push(entry);
+                }
+            }
+        } else {
+            paths.push(path.to_path_buf());
+        }
+    }
+
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    paths.retain(|path| seen.insert(path.clone()));
+    if paths.is_empty() {
+        return Err(Error::NoInput(inputs.join(" ")));
+    }
+
+    Ok(paths)
+}
+
+/// Параллельная обработка файлов. Результаты идут в порядке путей,
+/// ошибка в одном файле не прерывает обработку остальных
+pub fn process<T, F>(paths: &[PathBuf], job: F) -> Vec<Result<T, Error>>
+where
+    T: Send,
+    F: Fn(&Path) -> Result<T, Error> + Sync,
+{
+    paths.par_iter()
+        .map(|path| job(path))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Временный каталог с пустыми файлами, удаляется по завершении теста
+    struct Fixture(PathBuf);
+
+    impl Fixture {
+        fn new(name: &str, files: &[&str]) -> Fixture {
+            let root = std::env::temp_dir().join(format!("gpx-stamp-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            for file in files {
+                let path = root.join(file);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, "").unwrap();
+            }
+
+            Fixture(root)
+        }
+
+        fn path(&self, file: &str) -> String {
+            self.0.join(file).display().to_string()
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const FILES: [&str; 5] = ["b.gpx", "a.GPX", "notes.txt", "2023/june/c.gpx", "2023/d.gpx"];
+
+    #[test]
+    fn directories_are_walked_recursively() {
+        let fixture = Fixture::new("walk", &FILES);
+        let paths = expand(&[fixture.path("")]).unwrap();
+
+        let expected: Vec<PathBuf> = ["2023/d.gpx", "2023/june/c.gpx", "a.GPX", "b.gpx"].iter().map(|file| fixture.0.join(file)).collect();
+        assert_eq!(paths, expected);
+    }
+
+    #[test]
+    fn globs_match_files_and_directories() {
+        let fixture = Fixture::new("glob", &FILES);
+
+        let paths = expand(&[fixture.path("*.gpx")]).unwrap();
+        assert_eq!(paths, vec!(fixture.0.join("b.gpx")));
+
+        let paths = expand(&[fixture.path("**/*.gpx")]).unwrap();
+        assert_eq!(paths, vec!(fixture.0.join("2023/d.gpx"), fixture.0.join("2023/june/c.gpx"), fixture.0.join("b.gpx")));
+
+        let paths = expand(&[fixture.path("20*")]).unwrap();
+        assert_eq!(paths, vec!(fixture.0.join("2023/d.gpx"), fixture.0.join("2023/june/c.gpx")));
+    }
+
+    #[test]
+    fn globs_skip_other_files() {
+        let fixture = Fixture::new("glob-gpx", &FILES);
+
+        let paths = expand(&[fixture.path("*")]).unwrap();
+        assert_eq!(paths, vec!(fixture.0.join("2023/d.gpx"), fixture.0.join("2023/june/c.gpx"),
+                               fixture.0.join("a.GPX"), fixture.0.join("b.gpx")));
+
+        assert!(matches!(expand(&[fixture.path("*.txt")]), Err(Error::NoInput(_))));
+    }
+
+    #[test]
+    fn repeated_paths_are_dropped() {
+        let fixture = Fixture::new("dedup", &FILES);
+        let paths = expand(&[fixture.path("b.gpx"), fixture.path("2023"), fixture.path("*.gpx"), fixture.path("2023/d.gpx")]).unwrap();
+
+        assert_eq!(paths, vec!(fixture.0.join("b.gpx"), fixture.0.join("2023/d.gpx"), fixture.0.join("2023/june/c.gpx")));
+    }
+
+    #[test]
+    fn missing_files_and_bad_patterns() {
+        let fixture = Fixture::new("missing", &FILES);
+
+        assert_eq!(expand(&[fixture.path("none.gpx")]).unwrap(), vec!(fixture.0.join("none.gpx")));
+        assert!(matches!(expand(&[fixture.path("*.kml")]), Err(Error::NoInput(_))));
+        assert!(matches!(expand(&[fixture.path("[a")]), Err(Error::InvalidPattern(_))));
+    }
+}
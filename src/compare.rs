@@ -0,0 +1,94 @@
+use time::Duration;
+
+use crate::locale::{Lang, Messages};
+use crate::render::{format_distance, format_duration, format_elevation, format_speed};
+use crate::stamp::{Effort, Stamp};
+use crate::units::Units;
+
+
+// Разница с первым файлом со знаком. Значение форматируется по модулю
+fn signed(delta: f64, format: impl Fn(f64) -> String) -> String {
+    format!("{}{}", if delta < 0.0 { "-" } else { "+" }, format(delta.abs()))
+}
+
+// Значение и, для всех файлов кроме первого, разница с первым
+fn cells<F: Fn(f64) -> String>(values: &[Option<f64>], text: &Messages, format: F) -> Vec<String> {
+    let first = values.first().copied().flatten();
+
+    values.iter()
+        .enumerate()
+        .map(|(num, value)| match (value, first) {
+            (Some(value), Some(first)) if num > 0 => format!("{} ({})", format(*value), signed(value - first, &format)),
+            (Some(value), _) => format(*value),
+            (None, _) => text.unknown.to_string(),
+        })
+        .collect()
+}
+
+fn seconds(duration: Duration) -> f64 {
+    duration.as_seconds_f64()
+}
+
+// Результат на дистанции - время, на отрезке времени - дистанция
+fn effort_result(stamp: &Stamp, effort: Effort) -> Option<f64> {
+    let best = stamp.efforts.iter().find(|best| best.effort == effort)?;
+
+    Some(match effort {
+        Effort::Distance(_) => seconds(best.duration),
+        Effort::Duration(_) => best.distance as f64,
+    })
+}
+
+/// Сравнение нескольких файлов бок о бок: по строке на величину,
+/// по колонке на файл. Для всех файлов кроме первого в скобках
+/// указывается разница с первым
+pub fn compare_to_text(stamps: &[(&str, &Stamp)], lang: Lang, units: Units) -> String {
+    let text = lang.messages();
+    let distance = |meters: f64| format_distance(meters, text, units);
+    let time = |secs: f64| format_duration(Duration::seconds_f64(secs));
+    let speed = |speed: f64| format_speed(speed, text, units);
+    let elevation = |meters: f64| format_elevation(meters, text, units);
+    let values = |value: &dyn Fn(&Stamp) -> Option<f64>| -> Vec<Option<f64>> {
+        stamps.iter().map(|(_, stamp)| value(stamp)).collect()
+    };
+
+    let mut rows: Vec<(String, Vec<String>)> = vec!(
+        (text.length.to_string(), cells(&values(&|stamp| Some(stamp.header.length as f64)), text, distance)),
+        (text.total_time.to_string(), cells(&values(&|stamp| stamp.timing.map(|time| seconds(time.total))), text, time)),
+        (text.moving_time.to_string(), cells(&values(&|stamp| stamp.timing.map(|time| seconds(time.pure))), text, time)),
+        (text.avg_speed.to_string(), cells(&values(&|stamp| stamp.velocity.map(|velo| velo.average as f64)), text, speed)),
+        (text.max_speed.to_string(), cells(&values(&|stamp| stamp.velocity.map(|velo| velo.maximum as f64)), text, speed)),
+        (text.ascent.to_string(), cells(&values(&|stamp| stamp.elevation.map(|elev| elev.total as f64)), text, elevation)),
+        (text.descent.to_string(), cells(&values(&|stamp| stamp.elevation.map(|elev| elev.loss as f64)), text, elevation)),
+    );
+
+    // Лучшие результаты, найденные хотя бы в одном файле
+    let mut efforts: Vec<Effort> = vec!();
+    for (_, stamp) in stamps {
+        for best in &stamp.efforts {
+            if !efforts.contains(&best.effort) {
+                efforts.push(best.effort);
+            }
+        }
+    }
+    for effort in efforts {
+        let results = values(&|stamp| effort_result(stamp, effort));
+        let label = format!("{}: {}", text.best, match effort {
+            Effort::Distance(meters) => distance(meters as f64),
+            Effort::Duration(duration) => time(seconds(duration)),
+        });
+
+        rows.push((label, match effort {
+            Effort::Distance(_) => cells(&results, text, time),
+            Effort::Duration(_) => cells(&results, text, distance),
+        }));
+    }
+
+    let header: Vec<&str> = [""].into_iter().chain(stamps.iter().map(|(file, _)| *file)).collect();
+    let mut lines: Vec<String> = vec!(header.join(" | "));
+    for (label, cells) in rows {
+        lines.push(format!("{} | {}", label, cells.join(" | ")));
+    }
+
+    lines.join("\n")
+}
@@ -0,0 +1,265 @@
+use gpx::{Gpx, GpxVersion, Track, TrackSegment, Waypoint};
+use serde_json::{json, Value};
+use time::format_description::well_known::Rfc3339;
+use time::{Duration, OffsetDateTime};
+
+use crate::error::Error;
+use crate::simplify::minimize_way;
+
+
+// Время точки, если оно есть
+fn point_time(p: &Waypoint) -> Option<OffsetDateTime> {
+    p.time.map(OffsetDateTime::from)
+}
+
+// Время первой точки трека
+fn start_time(gpx: &Gpx) -> Option<OffsetDateTime> {
+    gpx.tracks.iter()
+        .flat_map(|track| track.segments.iter())
+        .flat_map(|segment| segment.points.first())
+        .find_map(point_time)
+}
+
+// Пустой GPX-файл версии 1.1 с создателем и метаданными исходного
+fn empty_like(gpx: &Gpx) -> Gpx {
+    Gpx {
+        version: GpxVersion::Gpx11,
+        creator: gpx.creator.clone(),
+        metadata: gpx.metadata.clone(),
+        ..Gpx::default()
+    }
+}
+
+// Трек с описанием исходного и заданными сегментами
+fn track_like(track: &Track, segments: Vec<TrackSegment>) -> Track {
+    Track { segments, ..track.clone() }
+}
+
+/// GPX-файл в XML версии 1.1
+pub fn write_gpx(gpx: &Gpx) -> Result<Vec<u8>, Error> {
+    let gpx = Gpx { version: GpxVersion::Gpx11, ..gpx.clone() };
+    let mut data: Vec<u8> = vec!();
+
+    gpx::write(&gpx, &mut data).map_err(|err| Error::Write(err.to_string()))?;
+
+    Ok(data)
+}
+
+/// Упрощение всех сегментов треков с помощью `minimize_way`. Угол
+/// спрямления задается в градусах, от 0 до 90
+pub fn simplify(gpx: &Gpx, angle: f64) -> Gpx {
+    let angle_mul = angle.clamp(0.0, 90.0) / 90.0;
+
+    Gpx {
+        version: GpxVersion::Gpx11,
+        tracks: gpx.tracks.iter()
+            .map(|track| track_like(track, track.segments.iter()
+                                    .map(|segment| TrackSegment { points: minimize_way(&segment.points, angle_mul) })
+                                    .collect()))
+            .collect(),
+        ..gpx.clone()
+    }
+}
+
+/// Разделение на отдельные файлы по сегментам треков. Если задан
+/// разрыв, сегменты дополнительно делятся там, где между соседними
+/// точками прошло больше этого времени. Пустые сегменты отбрасываются
+pub fn split(gpx: &Gpx, gap: Option<Duration>) -> Vec<Gpx> {
+    let mut parts: Vec<Gpx> = vec!();
+
+    for track in &gpx.tracks {
+        for segment in track.segments.iter().filter(|segment| !segment.points.is_empty()) {
+            let mut pieces: Vec<Vec<Waypoint>> = vec!(vec!());
+
+            for (num, p) in segment.points.iter().enumerate() {
+                let interval = num.checked_sub(1)
+                    .and_then(|prev| Some(point_time(p)? - point_time(&segment.points[prev])?));
+                if gap.zip(interval).is_some_and(|(gap, interval)| interval > gap) {
+                    pieces.push(vec!());
+                }
+                if let Some(piece) = pieces.last_mut() {
+                    piece.push(p.clone());
+                }
+            }
+
+            for points in pieces {
+                let mut part = empty_like(gpx);
+
+                part.tracks.push(track_like(track, vec!(TrackSegment { points })));
+                parts.push(part);
+            }
+        }
+    }
+
+    parts
+}
+
+/// Объединение нескольких файлов в один. Треки идут в порядке времени
+/// начала файлов, файлы без времени - в конце в исходном порядке
+pub fn merge(files: &[Gpx]) -> Gpx {
+    let mut order: Vec<&Gpx> = files.iter().collect();
+    order.sort_by_key(|gpx| (start_time(gpx).is_none(), start_time(gpx)));
+
+    let mut merged = order.first().map_or(Gpx { version: GpxVersion::Gpx11, ..Gpx::default() }, |first| empty_like(first));
+    for gpx in order {
+        merged.tracks.extend(gpx.tracks.iter().cloned());
+        merged.waypoints.extend(gpx.waypoints.iter().cloned());
+        merged.routes.extend(gpx.routes.iter().cloned());
+    }
+
+    merged
+}
+
+/// Треки в GeoJSON: по объекту MultiLineString на трек, координаты -
+/// долгота, широта и, если есть, высота. В свойствах - название, тип
+/// трека и время точек каждого сегмента
+pub fn to_geojson(gpx: &Gpx) -> Result<String, Error> {
+    let position = |p: &Waypoint| -> Value {
+        match p.elevation {
+            Some(elevation) => json!([p.point().x(), p.point().y(), elevation]),
+            None => json!([p.point().x(), p.point().y()]),
+        }
+    };
+    let time = |p: &Waypoint| -> Value {
+        point_time(p).and_then(|time| time.format(&Rfc3339).ok()).map_or(Value::Null, Value::String)
+    };
+
+    let features: Vec<Value> = gpx.tracks.iter()
+        .map(|track| json!({
+            "type": "Feature",
+            "geometry": {
+                "type": "MultiLineString",
+                "coordinates": track.segments.iter()
+                    .map(|segment| segment.points.iter().map(position).collect::<Vec<Value>>())
+                    .collect::<Vec<Vec<Value>>>(),
+            },
+            "properties": {
+                "name": track.name,
+                "type": track.type_,
+                "times": track.segments.iter()
+                    .map(|segment| segment.points.iter().map(time).collect::<Vec<Value>>())
+                    .collect::<Vec<Vec<Value>>>(),
+            },
+        }))
+        .collect();
+
+    serde_json::to_string_pretty(&json!({ "type": "FeatureCollection", "features": features }))
+        .map_err(|err| Error::Json(err.to_string()))
+}
+
+/// Точки треков в CSV: номер трека и сегмента, широта, долгота, высота и время
+pub fn points_to_csv(gpx: &Gpx) -> String {
+    let mut lines: Vec<String> = vec!("track,segment,lat,lon,elevation,time".to_string());
+
+    for (track_num, track) in gpx.tracks.iter().enumerate() {
+        for (segment_num, segment) in track.segments.iter().enumerate() {
+            for p in &segment.points {
+                lines.push(format!("{},{},{},{},{},{}",
+                                   track_num + 1,
+                                   segment_num + 1,
+                                   p.point().y(),
+                                   p.point().x(),
+                                   p.elevation.map_or(String::new(), |elevation| elevation.to_string()),
+                                   point_time(p).and_then(|time| time.format(&Rfc3339).ok()).unwrap_or_default()));
+            }
+        }
+    }
+
+    lines.join("\n")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Сегмент из точек, записанных в заданные минуты от начала суток
+    fn segment(minutes: &[i64]) -> TrackSegment {
+        let start = OffsetDateTime::from_unix_timestamp(1_686_355_200).unwrap();
+
+        TrackSegment {
+            points: minutes.iter()
+                .enumerate()
+                .map(|(num, minute)| {
+                    let mut p = Waypoint::new(geo_types::Point::new(37.6 + num as f64 * 0.001, 55.75));
+                    p.time = Some((start + Duration::minutes(*minute)).into());
+
+                    p
+                })
+                .collect(),
+        }
+    }
+
+    fn file(name: &str, segments: Vec<TrackSegment>) -> Gpx {
+        let mut track = Track::new();
+        track.name = Some(name.to_string());
+        track.segments = segments;
+
+        Gpx { creator: Some(format!("{} device", name)), tracks: vec!(track), ..Gpx::default() }
+    }
+
+    fn names(gpx: &Gpx) -> Vec<&str> {
+        gpx.tracks.iter().filter_map(|track| track.name.as_deref()).collect()
+    }
+
+    fn lengths(parts: &[Gpx]) -> Vec<usize> {
+        parts.iter().map(|part| part.tracks[0].segments[0].points.len()).collect()
+    }
+
+    #[test]
+    fn split_by_segments() {
+        let gpx = file("ride", vec!(segment(&[0, 1, 2]), TrackSegment::new(), segment(&[10, 11])));
+        let parts = split(&gpx, None);
+
+        assert_eq!(lengths(&parts), vec!(3, 2));
+        assert!(parts.iter().all(|part| names(part) == vec!("ride") && part.creator.as_deref() == Some("ride device")));
+    }
+
+    #[test]
+    fn split_by_gaps() {
+        let gpx = file("ride", vec!(segment(&[0, 1, 2, 30, 31, 32, 33, 90])));
+
+        assert_eq!(lengths(&split(&gpx, Some(Duration::minutes(10)))), vec!(3, 4, 1));
+        // Разрыв, равный порогу, сегмент не делит
+        assert_eq!(lengths(&split(&gpx, Some(Duration::minutes(28)))), vec!(7, 1));
+        assert_eq!(lengths(&split(&gpx, Some(Duration::hours(1)))), vec!(8));
+    }
+
+    #[test]
+    fn merge_orders_by_start_time() {
+        let untimed = |name: &str| {
+            let mut gpx = file(name, vec!(segment(&[0])));
+            gpx.tracks[0].segments[0].points[0].time = None;
+
+            gpx
+        };
+        let files = vec!(
+            untimed("first untimed"),
+            file("evening", vec!(segment(&[1200, 1210]))),
+            file("morning", vec!(segment(&[480, 490]))),
+            untimed("second untimed"),
+            file("noon", vec!(segment(&[720]))),
+        );
+        let merged = merge(&files);
+
+        assert_eq!(names(&merged), vec!("morning", "noon", "evening", "first untimed", "second untimed"));
+        assert_eq!(merged.creator.as_deref(), Some("morning device"));
+        assert_eq!(merged.version, GpxVersion::Gpx11);
+        assert!(merge(&[]).tracks.is_empty());
+    }
+
+    #[test]
+    fn split_and_merge_round_trip() {
+        let gpx = file("ride", vec!(segment(&[0, 1, 2, 30, 31])));
+        let mut parts = split(&gpx, Some(Duration::minutes(10)));
+        parts.reverse();
+
+        let merged = merge(&parts);
+        let points: Vec<Waypoint> = merged.tracks.iter().flat_map(|track| track.segments[0].points.clone()).collect();
+        assert_eq!(points, gpx.tracks[0].segments[0].points);
+
+        let written = write_gpx(&merged).unwrap();
+        let read = gpx::read(written.as_slice()).unwrap();
+        assert_eq!(names(&read), vec!("ride", "ride"));
+    }
+}
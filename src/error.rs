@@ -0,0 +1,116 @@
+use std::fmt;
+use std::io;
+
+use gpx::errors::GpxError;
+
+use crate::locale::{fill, Lang};
+
+
+/// Ошибки, возникающие при чтении GPX-файла, подсчете статистики и отрисовке
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error), // Файл не удалось открыть или сохранить
+    Parse(GpxError), // Файл не является корректным GPX
+    NoTracks, // В файле нет ни одной точки трека
+    NoTime, // У точек трека нет временных меток
+    NoElevation, // У точек трека нет данных о высоте
+    ShortRoute, // Маршрут слишком короткий для подсчета статистики
+    UnknownActivity(String), // Неизвестный тип активности
+    InvalidEffort(String), // Некорректная дистанция или продолжительность для лучших результатов
+    InvalidSmoothing(String), // Неизвестный способ сглаживания скорости
+    UnknownProjection(String), // Неизвестная картографическая проекция
+    UnknownTheme(String), // Нет ни встроенной темы, ни файла с таким названием
+    InvalidTheme(String), // Файл темы оформления не корректный
+    UnknownFormat(String), // Неизвестный формат вывода
+    UnknownLang(String), // Неизвестный язык вывода
+    UnknownUnits(String), // Неизвестная система единиц измерения
+    Rasterize(String), // Не удалось получить растровое изображение
+    Pdf(String), // Не удалось сформировать PDF-документ
+    Json(String), // Не удалось сформировать JSON
+    InvalidPattern(String), // Некорректный шаблон имени файла
+    NoInput(String), // Ни один путь или шаблон не дал ни одного файла
+    UnknownPeriod(String), // Неизвестный период итогов
+    Database(String), // Ошибка базы библиотеки активностей
+    InvalidArea(String), // Некорректная область отбора
+    NotInLibrary(String), // В библиотеке нет активности с таким идентификатором
+    AmbiguousId(String), // Идентификатору соответствует несколько активностей
+    Write(String), // Не удалось сформировать GPX-файл
+    UnsupportedFormat(String), // Формат вывода не поддерживается командой
+    NoStdout, // Результат команды нельзя вывести в стандартный вывод
+    FileExists(String), // Выходной файл уже существует, а заменять его нельзя
+    OverwriteDeclined(String), // Пользователь отказался заменять выходной файл
+}
+
+impl Error {
+    /// Сообщение об ошибке на заданном языке
+    pub fn localize(&self, lang: Lang) -> String {
+        let text = lang.messages();
+
+        match self {
+            Error::Io(err) => fill(text.error_io, &[err]),
+            Error::Parse(err) => fill(text.error_parse, &[err]),
+            Error::NoTracks => text.error_no_tracks.to_string(),
+            Error::NoTime => text.error_no_time.to_string(),
+            Error::NoElevation => text.error_no_elevation.to_string(),
+            Error::ShortRoute => text.error_short_route.to_string(),
+            Error::UnknownActivity(name) => fill(text.error_unknown_activity, &[name]),
+            Error::InvalidEffort(value) => fill(text.error_invalid_effort, &[value]),
+            Error::InvalidSmoothing(value) => fill(text.error_invalid_smoothing, &[value]),
+            Error::UnknownProjection(name) => fill(text.error_unknown_projection, &[name]),
+            Error::UnknownTheme(name) => fill(text.error_unknown_theme, &[name]),
+            Error::InvalidTheme(reason) => fill(text.error_invalid_theme, &[reason]),
+            Error::UnknownFormat(name) => fill(text.error_unknown_format, &[name]),
+            Error::UnknownLang(name) => fill(text.error_unknown_lang, &[name]),
+            Error::UnknownUnits(name) => fill(text.error_unknown_units, &[name]),
+            Error::Rasterize(reason) => fill(text.error_rasterize, &[reason]),
+            Error::Pdf(reason) => fill(text.error_pdf, &[reason]),
+            Error::Json(reason) => fill(text.error_json, &[reason]),
+            Error::InvalidPattern(reason) => fill(text.error_invalid_pattern, &[reason]),
+            Error::NoInput(inputs) => fill(text.error_no_input, &[inputs]),
+            Error::UnknownPeriod(name) => fill(text.error_unknown_period, &[name]),
+            Error::Database(reason) => fill(text.error_database, &[reason]),
+            Error::InvalidArea(value) => fill(text.error_invalid_area, &[value]),
+            Error::NotInLibrary(id) => fill(text.error_not_in_library, &[id]),
+            Error::AmbiguousId(id) => fill(text.error_ambiguous_id, &[id]),
+            Error::Write(reason) => fill(text.error_write, &[reason]),
+            Error::UnsupportedFormat(name) => fill(text.error_unsupported_format, &[name]),
+            Error::NoStdout => text.error_no_stdout.to_string(),
+            Error::FileExists(path) => fill(text.error_file_exists, &[path]),
+            Error::OverwriteDeclined(path) => fill(text.error_overwrite_declined, &[path]),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.localize(Lang::default()))
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Parse(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Error {
+        Error::Database(err.to_string())
+    }
+}
+
+impl From<GpxError> for Error {
+    fn from(err: GpxError) -> Error {
+        Error::Parse(err)
+    }
+}
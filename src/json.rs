@@ -0,0 +1,318 @@
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+use time::{Date, Duration, OffsetDateTime};
+use time::format_description::well_known::Rfc3339;
+
+use crate::error::Error;
+use crate::stamp::Stamp;
+use crate::summary::Summary;
+
+
+/// Версия схемы JSON-представления штампа, см. schema/stamp-v1.schema.json.
+/// Увеличивается при любом несовместимом изменении полей
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct Document<'a> {
+    schema_version: u32,
+    #[serde(flatten)]
+    stamp: &'a Stamp,
+}
+
+#[derive(Serialize)]
+struct SummaryDocument<'a> {
+    schema_version: u32,
+    #[serde(flatten)]
+    summary: &'a Summary,
+}
+
+#[derive(Serialize)]
+struct FileStamp<'a> {
+    file: &'a str,
+    #[serde(flatten)]
+    stamp: &'a Stamp,
+}
+
+#[derive(Serialize)]
+struct FileError<'a> {
+    file: &'a str,
+    error: &'a str,
+}
+
+#[derive(Serialize)]
+struct Batch<'a> {
+    schema_version: u32,
+    files: Vec<FileStamp<'a>>,
+    errors: Vec<FileError<'a>>,
+}
+
+/// Штамп в JSON: расстояния и высоты в метрах, скорости в метрах в секунду,
+/// продолжительности и даты в ISO 8601. Описание полей - в JSON Schema
+/// schema/stamp-v1.schema.json
+pub fn to_json(stamp: &Stamp) -> Result<String, Error> {
+    let document = Document { schema_version: SCHEMA_VERSION, stamp };
+
+    serde_json::to_string_pretty(&document).map_err(|err| Error::Json(err.to_string()))
+}
+
+/// Штампы нескольких файлов и сообщения об ошибках обработки остальных
+/// файлов в одном JSON-документе по той же схеме
+pub fn batch_to_json(stamps: &[(&str, &Stamp)], errors: &[(&str, String)]) -> Result<String, Error> {
+    let document = Batch {
+        schema_version: SCHEMA_VERSION,
+        files: stamps.iter().map(|(file, stamp)| FileStamp { file, stamp }).collect(),
+        errors: errors.iter().map(|(file, error)| FileError { file, error }).collect(),
+    };
+
+    serde_json::to_string_pretty(&document).map_err(|err| Error::Json(err.to_string()))
+}
+
+/// Штамп из JSON, сформированного `to_json`. Документы другой
+/// версии схемы не принимаются. Скорости и продолжительности в JSON
+/// округлены до миллиметров в секунду и миллисекунд, поэтому прочитанный
+/// штамп совпадает с исходным в пределах этой точности
+pub fn from_json(text: &str) -> Result<Stamp, Error> {
+    let document: serde_json::Value = serde_json::from_str(text).map_err(|err| Error::Json(err.to_string()))?;
+    let version = document.get("schema_version").and_then(|version| version.as_u64());
+    if version != Some(SCHEMA_VERSION as u64) {
+        return Err(Error::Json(format!("unsupported schema version: {}", version.map_or("none".to_string(), |v| v.to_string()))));
+    }
+
+    serde_json::from_value(document).map_err(|err| Error::Json(err.to_string()))
+}
+
+/// Итоги по периодам в JSON, описание полей - в JSON Schema
+/// schema/summary-v1.schema.json
+pub fn summary_to_json(summary: &Summary) -> Result<String, Error> {
+    let document = SummaryDocument { schema_version: SCHEMA_VERSION, summary };
+
+    serde_json::to_string_pretty(&document).map_err(|err| Error::Json(err.to_string()))
+}
+
+// Продолжительность в виде "PT1H27M10S". Доли секунды выводятся,
+// только если они есть
+fn iso_duration(duration: &Duration) -> String {
+    let hours = duration.whole_hours();
+    let minutes = duration.whole_minutes() % 60;
+    let seconds = duration.as_seconds_f64() - duration.whole_minutes() as f64 * 60.0;
+
+    let mut text = String::from("PT");
+    if hours != 0 {
+        text.push_str(&format!("{}H", hours));
+    }
+    if minutes != 0 {
+        text.push_str(&format!("{}M", minutes));
+    }
+    if seconds != 0.0 || (hours == 0 && minutes == 0) {
+        let seconds = format!("{:.3}", seconds);
+
+        text.push_str(seconds.trim_end_matches('0').trim_end_matches('.'));
+        text.push('S');
+    }
+
+    text
+}
+
+// Разбор продолжительности, записанной `iso_duration`
+fn parse_iso_duration(text: &str) -> Option<Duration> {
+    let mut rest = text.strip_prefix("PT").filter(|rest| !rest.is_empty())?;
+    let mut seconds = 0.0;
+
+    while !rest.is_empty() {
+        let end = rest.find(['H', 'M', 'S'])?;
+        let value: f64 = rest[..end].parse().ok()?;
+
+        seconds += value * match &rest[end..=end] {
+            "H" => 3600.0,
+            "M" => 60.0,
+            _ => 1.0,
+        };
+        rest = &rest[end + 1..];
+    }
+
+    Some(Duration::seconds_f64(seconds))
+}
+
+pub(crate) mod duration {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&iso_duration(duration))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let text = String::deserialize(deserializer)?;
+
+        parse_iso_duration(&text).ok_or_else(|| de::Error::custom(format!("invalid duration: {}", text)))
+    }
+}
+
+pub(crate) mod datetime {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(date: &OffsetDateTime, serializer: S) -> Result<S::Ok, S::Error> {
+        let text = date.format(&Rfc3339).map_err(ser::Error::custom)?;
+
+        serializer.serialize_str(&text)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<OffsetDateTime, D::Error> {
+        let text = String::deserialize(deserializer)?;
+
+        OffsetDateTime::parse(&text, &Rfc3339).map_err(de::Error::custom)
+    }
+}
+
+pub(crate) mod option_datetime {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(date: &Option<OffsetDateTime>, serializer: S) -> Result<S::Ok, S::Error> {
+        match date {
+            Some(date) => datetime::serialize(date, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<OffsetDateTime>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|text| OffsetDateTime::parse(&text, &Rfc3339).map_err(de::Error::custom))
+            .transpose()
+    }
+}
+
+// Дата без времени в виде "2023-06-05"
+pub(crate) mod date {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(date: &Date, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&date.to_string())
+    }
+}
+
+// Скорости хранятся в метрах/час, в JSON выводятся в метрах в секунду
+// с точностью до миллиметра
+pub(crate) mod speed {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(speed: &usize, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64((*speed as f64 / 3.6).round() / 1000.0)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+        let speed = f64::deserialize(deserializer)?;
+
+        Ok((speed * 3600.0).round() as usize)
+    }
+}
+
+pub(crate) mod option_speed {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(speed: &Option<usize>, serializer: S) -> Result<S::Ok, S::Error> {
+        match speed {
+            Some(value) => speed::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<usize>, D::Error> {
+        let speed = Option::<f64>::deserialize(deserializer)?;
+
+        Ok(speed.map(|speed| (speed * 3600.0).round() as usize))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use gpx::{Gpx, GpxVersion, Track, TrackSegment, Waypoint};
+
+    use super::*;
+    use crate::stamp::{Effort, Options};
+
+    // Трек на 300 точек с переменной скоростью, подъемами и паузой
+    fn gpx() -> Gpx {
+        let start = OffsetDateTime::from_unix_timestamp(1_686_380_400).unwrap();
+        let mut segment = TrackSegment::new();
+        let mut lon = 37.6;
+        let mut secs = 0;
+
+        for num in 0..300 {
+            let mut p = Waypoint::new(geo_types::Point::new(lon, 55.75));
+            p.elevation = Some(150.0 + 40.0 * (num as f64 / 30.0).sin());
+            p.time = Some((start + Duration::seconds(secs)).into());
+            segment.points.push(p);
+
+            lon += 0.0003 + 0.0001 * (num % 7) as f64;
+            secs += if num == 150 { 300 } else { 5 };
+        }
+
+        let mut track = Track::new();
+        track.name = Some("Test".to_string());
+        track.segments.push(segment);
+
+        Gpx { version: GpxVersion::Gpx11, tracks: vec!(track), ..Gpx::default() }
+    }
+
+    #[test]
+    fn stamp_round_trip() {
+        let options = Options {
+            efforts: vec!(Effort::Distance(1000), Effort::Duration(Duration::minutes(5))),
+            ..Options::default()
+        };
+        let stamp = Stamp::new(&gpx(), &options).unwrap();
+        let text = to_json(&stamp).unwrap();
+        let read = from_json(&text).unwrap();
+
+        assert_eq!(to_json(&read).unwrap(), text);
+        assert_eq!(from_json(&text).unwrap(), read);
+
+        // Значения, записанные без округления, совпадают точно
+        assert_eq!(read.header, stamp.header);
+        assert_eq!(read.elevation, stamp.elevation);
+        assert_eq!(read.efforts, stamp.efforts);
+        assert_eq!(read.pauses, stamp.pauses);
+        assert_eq!(read.gradient, stamp.gradient);
+
+        // Скорости округлены до миллиметра в секунду, время - до миллисекунды
+        let (velocity, original) = (read.velocity.unwrap(), stamp.velocity.unwrap());
+        assert!(velocity.average.abs_diff(original.average) <= 2);
+        assert!(velocity.maximum.abs_diff(original.maximum) <= 2);
+        assert_eq!(read.splits.len(), stamp.splits.len());
+        for (split, original) in read.splits.iter().zip(&stamp.splits) {
+            let delta = split.timing.unwrap().total - original.timing.unwrap().total;
+
+            assert_eq!(split.length, original.length);
+            assert!(delta.abs() < Duration::milliseconds(1));
+            assert!(split.speed.unwrap().abs_diff(original.speed.unwrap()) <= 2);
+        }
+    }
+
+    #[test]
+    fn rejects_other_schema_versions() {
+        let stamp = Stamp::new(&gpx(), &Options::default()).unwrap();
+        let text = to_json(&stamp).unwrap().replacen("\"schema_version\": 1", "\"schema_version\": 2", 1);
+
+        assert!(matches!(from_json(&text), Err(Error::Json(_))));
+        assert!(matches!(from_json("{}"), Err(Error::Json(_))));
+    }
+
+    #[test]
+    fn iso_durations() {
+        let cases = [
+            (Duration::ZERO, "PT0S"),
+            (Duration::seconds(45), "PT45S"),
+            (Duration::minutes(5), "PT5M"),
+            (Duration::hours(2), "PT2H"),
+            (Duration::seconds(5230), "PT1H27M10S"),
+            (Duration::milliseconds(1500), "PT1.5S"),
+        ];
+
+        for (duration, text) in cases {
+            assert_eq!(iso_duration(&duration), text);
+            assert_eq!(parse_iso_duration(text), Some(duration));
+        }
+        assert_eq!(parse_iso_duration("PT"), None);
+        assert_eq!(parse_iso_duration("P1D"), None);
+        assert_eq!(parse_iso_duration("PT5X"), None);
+    }
+}
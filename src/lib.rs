@@ -0,0 +1,52 @@
+//! Сводная статистика по GPX-трекам: протяженность, время, скорость и подъем,
+//! а также отрисовка "штампа" трека в SVG.
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use std::io::BufReader;
+//!
+//! use gpx_stamp::{Lang, Stamp, Units, to_text};
+//!
+//! let gpx = gpx::read(BufReader::new(File::open("track.gpx").unwrap())).unwrap();
+//! let stamp = Stamp::try_from(&gpx).unwrap();
+//! println!("{}", to_text(&stamp, Lang::En, Units::Metric));
+//! ```
+
+pub mod activity;
+pub mod batch;
+pub mod compare;
+pub mod edit;
+pub mod error;
+pub mod json;
+pub mod library;
+pub mod locale;
+pub mod projection;
+pub mod raster;
+pub mod render;
+pub mod report;
+pub mod simplify;
+pub mod stamp;
+pub mod stat;
+pub mod summary;
+pub mod table;
+pub mod theme;
+pub mod units;
+
+pub use crate::activity::{Activity, Profile};
+pub use crate::compare::compare_to_text;
+pub use crate::edit::{merge, points_to_csv, simplify, split, to_geojson, write_gpx};
+pub use crate::error::Error;
+pub use crate::json::{from_json, to_json, summary_to_json};
+pub use crate::library::{Area, Entry, Filter, Library};
+pub use crate::locale::Lang;
+pub use crate::projection::Projection;
+pub use crate::raster::{to_png, RasterSize};
+pub use crate::render::{to_svg, to_text, RenderOptions};
+pub use crate::report::to_pdf;
+pub use crate::simplify::minimize_way;
+pub use crate::stamp::{BestEffort, Climb, ClimbCategory, Effort, Gradient, Options, Pause, Split, Stamp, segments};
+pub use crate::stat::find_pauses;
+pub use crate::summary::{summarize, summary_to_svg, summary_to_text, Period, Summary, Total};
+pub use crate::table::{to_csv, to_markdown};
+pub use crate::theme::Theme;
+pub use crate::units::Units;
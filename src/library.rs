@@ -0,0 +1,535 @@
+use std::collections::HashSet;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use geo_types::Point as GeoPoint;
+use gpx::Waypoint;
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use time::{Date, Duration, Month, OffsetDateTime};
+
+use crate::activity::Activity;
+use crate::error::Error;
+use crate::json::{self, from_json, to_json};
+use crate::locale::Lang;
+use crate::render::format_distance;
+use crate::simplify::minimize_way;
+use crate::stamp::Stamp;
+use crate::units::Units;
+
+
+// Таблица активностей. Штамп хранится в JSON по схеме `to_json`,
+// дата начала - в секундах Unix-времени, для отбора по периоду
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS activities (
+        id TEXT PRIMARY KEY,
+        path TEXT NOT NULL,
+        start INTEGER,
+        activity TEXT NOT NULL,
+        distance INTEGER NOT NULL,
+        west REAL NOT NULL,
+        south REAL NOT NULL,
+        east REAL NOT NULL,
+        north REAL NOT NULL,
+        stamp TEXT NOT NULL,
+        geometry TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS activities_start ON activities (start);
+";
+
+/// Длина сокращенного идентификатора в списке активностей
+pub const SHORT_ID: usize = 12;
+
+const COLUMNS: &str = "id, path, west, south, east, north, stamp, geometry";
+
+/// Прямоугольная область в градусах долготы и широты
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Area {
+    pub west: f64,
+    pub south: f64,
+    pub east: f64,
+    pub north: f64,
+}
+
+impl Area {
+    /// Область, охватывающая все точки пути
+    pub fn of(ways: &[&[Waypoint]]) -> Option<Area> {
+        let mut points = ways.iter().flat_map(|way| way.iter()).map(|p| p.point());
+        let first = points.next()?;
+        let start = Area { west: first.x(), south: first.y(), east: first.x(), north: first.y() };
+
+        Some(points.fold(start, |area, p| Area {
+            west: area.west.min(p.x()),
+            south: area.south.min(p.y()),
+            east: area.east.max(p.x()),
+            north: area.north.max(p.y()),
+        }))
+    }
+
+    pub fn contains(&self, p: &Waypoint) -> bool {
+        let (lon, lat) = (p.point().x(), p.point().y());
+
+        lon >= self.west && lon <= self.east && lat >= self.south && lat <= self.north
+    }
+
+    /// Пересекает ли отрезок между двумя точками область. Отрезок
+    /// отсекается по границам области методом Лианга-Барски
+    pub fn crosses(&self, p1: &Waypoint, p2: &Waypoint) -> bool {
+        let (x, y) = (p1.point().x(), p1.point().y());
+        let (dx, dy) = (p2.point().x() - x, p2.point().y() - y);
+        let (mut enter, mut leave) = (0.0f64, 1.0f64);
+
+        for (p, q) in [(-dx, x - self.west), (dx, self.east - x), (-dy, y - self.south), (dy, self.north - y)] {
+            if p == 0.0 {
+                // Отрезок параллелен границе и лежит снаружи
+                if q < 0.0 {
+                    return false;
+                }
+            } else if p < 0.0 {
+                enter = enter.max(q / p);
+            } else {
+                leave = leave.min(q / p);
+            }
+        }
+
+        enter <= leave
+    }
+
+    /// Проходит ли путь через область. Проверяются отрезки между точками,
+    /// поэтому упрощенный путь с редкими точками тоже не пропускает область
+    pub fn touches(&self, way: &[Waypoint]) -> bool {
+        match way {
+            [p] => self.contains(p),
+            _ => way.windows(2).any(|pair| self.crosses(&pair[0], &pair[1])),
+        }
+    }
+}
+
+impl fmt::Display for Area {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{},{},{}", self.west, self.south, self.east, self.north)
+    }
+}
+
+/// Разбор области вида "запад,юг,восток,север", например "37.3,55.5,37.9,55.9".
+/// Западная граница не может быть восточнее восточной, так что область,
+/// пересекающую 180-й меридиан, нужно задавать двумя запросами
+impl FromStr for Area {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values: Vec<f64> = s.split(',')
+            .map(|value| value.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| Error::InvalidArea(s.to_string()))?;
+
+        match values[..] {
+            [west, south, east, north] if west <= east && south <= north => Ok(Area { west, south, east, north }),
+            _ => Err(Error::InvalidArea(s.to_string())),
+        }
+    }
+}
+
+/// Условия отбора активностей. Незаданные условия не проверяются
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Filter {
+    pub from: Option<Date>, // Первый день периода, по дате начала в UTC
+    pub to: Option<Date>, // Последний день периода включительно
+    pub activity: Option<Activity>,
+    pub min_distance: Option<f64>, // Протяженность в метрах
+    pub max_distance: Option<f64>,
+    pub area: Option<Area>, // Область, через которую проходит маршрут
+}
+
+/// Активность в библиотеке
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub id: String, // Хеш SHA-256 содержимого GPX-файла
+    pub path: String, // Путь к файлу на момент импорта
+    pub stamp: Stamp,
+    pub area: Area, // Область, охватывающая маршрут
+    pub ways: Vec<Vec<Waypoint>>, // Сегменты пути, упрощенные с помощью `minimize_way`
+}
+
+impl Entry {
+    pub fn new(id: String, path: String, stamp: Stamp, ways: &[&[Waypoint]]) -> Result<Entry, Error> {
+        Ok(Entry {
+            id,
+            path,
+            stamp,
+            area: Area::of(ways).ok_or(Error::NoTracks)?,
+            ways: ways.iter().map(|way| minimize_way(way, 12.0 / 90.0)).collect(),
+        })
+    }
+
+    /// Сокращенный идентификатор из первых SHORT_ID символов.
+    /// Более короткий идентификатор возвращается целиком
+    pub fn short_id(&self) -> &str {
+        self.id.get(..SHORT_ID).unwrap_or(&self.id)
+    }
+
+    /// Сегменты пути для отрисовки
+    pub fn ways(&self) -> Vec<&[Waypoint]> {
+        self.ways.iter().map(|way| way.as_slice()).collect()
+    }
+}
+
+// Точка упрощенного пути в базе: долгота, широта, высота и время.
+// Время нужно для отрисовки участков лучших результатов
+#[derive(Serialize, Deserialize)]
+struct Point(f64, f64, Option<f64>, #[serde(with = "json::option_datetime")] Option<OffsetDateTime>);
+
+fn geometry_to_json(ways: &[Vec<Waypoint>]) -> Result<String, Error> {
+    let ways: Vec<Vec<Point>> = ways.iter()
+        .map(|way| way.iter()
+             .map(|p| Point(p.point().x(), p.point().y(), p.elevation, p.time.map(OffsetDateTime::from)))
+             .collect())
+        .collect();
+
+    serde_json::to_string(&ways).map_err(|err| Error::Json(err.to_string()))
+}
+
+fn geometry_from_json(text: &str) -> Result<Vec<Vec<Waypoint>>, Error> {
+    let ways: Vec<Vec<Point>> = serde_json::from_str(text).map_err(|err| Error::Json(err.to_string()))?;
+
+    Ok(ways.into_iter()
+       .map(|way| way.into_iter()
+            .map(|Point(lon, lat, elevation, time)| {
+                let mut p = Waypoint::new(GeoPoint::new(lon, lat));
+                p.elevation = elevation;
+                p.time = time.map(gpx::Time::from);
+
+                p
+            })
+            .collect())
+       .collect())
+}
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<(String, String, Area, String, String)> {
+    Ok((
+        row.get(0)?,
+        row.get(1)?,
+        Area { west: row.get(2)?, south: row.get(3)?, east: row.get(4)?, north: row.get(5)? },
+        row.get(6)?,
+        row.get(7)?,
+    ))
+}
+
+fn entry_from_columns((id, path, area, stamp, geometry): (String, String, Area, String, String)) -> Result<Entry, Error> {
+    Ok(Entry { id, path, area, stamp: from_json(&stamp)?, ways: geometry_from_json(&geometry)? })
+}
+
+/// Идентификатор GPX-файла - хеш SHA-256 его содержимого. Один и тот же
+/// файл под другим именем или в другом каталоге получает тот же идентификатор
+pub fn content_id(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Разбор даты вида "2023-06-10"
+pub fn parse_date(value: &str) -> Option<Date> {
+    let parts: Vec<&str> = value.trim().split('-').collect();
+    let [year, month, day] = parts[..] else {
+        return None;
+    };
+    let month = Month::try_from(month.parse::<u8>().ok()?).ok()?;
+
+    Date::from_calendar_date(year.parse().ok()?, month, day.parse().ok()?).ok()
+}
+
+/// Библиотека активностей во встроенной базе SQLite. Хранит штампы,
+/// области и упрощенные пути, чтобы итоги и отбор не требовали
+/// повторного разбора GPX-файлов. Штамп хранится в JSON, поэтому
+/// скорости сохраняются с точностью до миллиметра в секунду
+pub struct Library {
+    connection: Connection,
+}
+
+impl Library {
+    /// Путь к базе по умолчанию: переменная GPX_STAMP_LIBRARY, затем
+    /// каталог данных пользователя по XDG_DATA_HOME или HOME
+    pub fn default_path() -> PathBuf {
+        if let Some(path) = env::var_os("GPX_STAMP_LIBRARY").filter(|path| !path.is_empty()) {
+            return PathBuf::from(path);
+        }
+
+        let data = env::var_os("XDG_DATA_HOME")
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("share")))
+            .unwrap_or_default();
+
+        data.join("gpx-stamp").join("library.sqlite")
+    }
+
+    /// Открытие базы, при необходимости вместе с созданием файла и таблиц
+    pub fn open(path: &Path) -> Result<Library, Error> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(Library { connection })
+    }
+
+    /// Идентификаторы всех активностей
+    pub fn ids(&self) -> Result<HashSet<String>, Error> {
+        let mut statement = self.connection.prepare("SELECT id FROM activities")?;
+        let ids = statement.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<HashSet<String>>>()?;
+
+        Ok(ids)
+    }
+
+    /// Добавление активностей одной транзакцией. Уже имеющиеся
+    /// активности не изменяются. Возвращает кол-во добавленных
+    pub fn insert(&mut self, entries: &[Entry]) -> Result<usize, Error> {
+        let transaction = self.connection.transaction()?;
+        let mut added = 0;
+
+        {
+            let mut statement = transaction.prepare(
+                "INSERT OR IGNORE INTO activities (id, path, start, activity, distance, west, south, east, north, stamp, geometry)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)")?;
+            for entry in entries {
+                let head = &entry.stamp.header;
+                let activity = serde_json::to_value(head.activity).map_err(|err| Error::Json(err.to_string()))?;
+
+                added += statement.execute(params![
+                    entry.id,
+                    entry.path,
+                    head.date.map(|date| date.unix_timestamp()),
+                    activity.as_str(),
+                    head.length as i64,
+                    entry.area.west,
+                    entry.area.south,
+                    entry.area.east,
+                    entry.area.north,
+                    to_json(&entry.stamp)?,
+                    geometry_to_json(&entry.ways)?,
+                ])?;
+            }
+        }
+        transaction.commit()?;
+
+        Ok(added)
+    }
+
+    /// Активности, удовлетворяющие условиям, по дате начала. Дата, тип,
+    /// протяженность и пересечение областей проверяются в базе, а прохождение
+    /// маршрута через область - по отрезкам упрощенного пути
+    pub fn query(&self, filter: &Filter) -> Result<Vec<Entry>, Error> {
+        let midnight = |date: Date| date.midnight().assume_utc().unix_timestamp();
+        let activity = filter.activity
+            .map(|activity| serde_json::to_value(activity).map_err(|err| Error::Json(err.to_string())))
+            .transpose()?;
+        let area = filter.area;
+
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {} FROM activities
+             WHERE (?1 IS NULL OR start >= ?1) AND (?2 IS NULL OR start < ?2)
+               AND (?3 IS NULL OR activity = ?3)
+               AND (?4 IS NULL OR distance >= ?4) AND (?5 IS NULL OR distance <= ?5)
+               AND (?6 IS NULL OR (east >= ?6 AND north >= ?7 AND west <= ?8 AND south <= ?9))
+             ORDER BY start IS NULL, start, path", COLUMNS))?;
+        let rows = statement.query_map(params![
+            filter.from.map(midnight),
+            filter.to.map(|date| midnight(date + Duration::DAY)),
+            activity.as_ref().and_then(|activity| activity.as_str()),
+            filter.min_distance,
+            filter.max_distance,
+            area.map(|area| area.west),
+            area.map(|area| area.south),
+            area.map(|area| area.east),
+            area.map(|area| area.north),
+        ], entry_from_row)?;
+
+        let mut entries: Vec<Entry> = vec!();
+        for row in rows {
+            let entry = entry_from_columns(row?)?;
+
+            if area.is_none_or(|area| entry.ways.iter().any(|way| area.touches(way))) {
+                entries.push(entry);
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Активность по идентификатору или его началу
+    pub fn get(&self, id: &str) -> Result<Entry, Error> {
+        let prefix = id.trim().to_lowercase();
+        if prefix.is_empty() || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(Error::NotInLibrary(id.to_string()));
+        }
+
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {} FROM activities WHERE substr(id, 1, length(?1)) = ?1 LIMIT 2", COLUMNS))?;
+        let mut rows = statement.query_map(params![prefix], entry_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        match rows.len() {
+            0 => Err(Error::NotInLibrary(id.to_string())),
+            1 => entry_from_columns(rows.remove(0)),
+            _ => Err(Error::AmbiguousId(id.to_string())),
+        }
+    }
+}
+
+/// Краткий список активностей: сокращенный идентификатор, дата, тип,
+/// протяженность, название трека и путь к файлу
+pub fn to_list(entries: &[Entry], lang: Lang, units: Units) -> String {
+    let text = lang.messages();
+    let header = [text.id, text.date_utc, text.activity_short, text.length, text.track, text.file];
+
+    let mut rows: Vec<String> = vec!(header.join(" | "));
+    for entry in entries {
+        let head = &entry.stamp.header;
+        let date = head.date.map_or(text.unknown.to_string(), |date| {
+            format!("{} {:02}:{:02}:{:02}", date.date(), date.hour(), date.minute(), date.second())
+        });
+
+        rows.push([
+            entry.short_id().to_string(),
+            date,
+            lang.activity(head.activity).to_string(),
+            format_distance(head.length as f64, text, units),
+            head.track.clone().unwrap_or(text.unknown.to_string()),
+            entry.path.clone(),
+        ].join(" | "));
+    }
+
+    rows.join("\n")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use gpx::{Gpx, Track, TrackSegment};
+
+    use super::*;
+    use crate::stamp::Options;
+
+    // Активность по прямой между двумя точками, начавшаяся в полдень дня date
+    fn entry(id: &str, date: (i32, u8, u8), activity: Activity, from: (f64, f64), to: (f64, f64)) -> Entry {
+        let start = Date::from_calendar_date(date.0, Month::try_from(date.1).unwrap(), date.2).unwrap()
+            .midnight().assume_utc() + Duration::hours(12);
+        let mut segment = TrackSegment::new();
+        for num in 0..=10 {
+            let part = num as f64 / 10.0;
+            let mut p = Waypoint::new(GeoPoint::new(from.0 + (to.0 - from.0) * part, from.1 + (to.1 - from.1) * part));
+            p.time = Some((start + Duration::minutes(num)).into());
+            segment.points.push(p);
+        }
+        let mut track = Track::new();
+        track.segments.push(segment);
+
+        let gpx = Gpx { tracks: vec!(track), ..Gpx::default() };
+        let options = Options { activity: Some(activity), ..Options::default() };
+        let stamp = Stamp::new(&gpx, &options).unwrap();
+        let way = gpx.tracks[0].segments[0].points.as_slice();
+
+        Entry::new(id.to_string(), format!("{}.gpx", id), stamp, &[way]).unwrap()
+    }
+
+    fn library(entries: &[Entry]) -> Library {
+        let mut library = Library::open(Path::new(":memory:")).unwrap();
+        library.insert(entries).unwrap();
+
+        library
+    }
+
+    fn paths(entries: &[Entry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.path.as_str()).collect()
+    }
+
+    #[test]
+    fn content_ids() {
+        assert_eq!(content_id(b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(content_id(b"<gpx/>"), content_id(b"<gpx/>"));
+        assert_ne!(content_id(b"<gpx/>"), content_id(b"<gpx />"));
+    }
+
+    #[test]
+    fn known_activities_are_skipped() {
+        let ride = entry(&content_id(b"ride"), (2023, 6, 10), Activity::Cycling, (37.0, 55.0), (38.0, 55.0));
+        let run = entry(&content_id(b"run"), (2023, 6, 20), Activity::Running, (37.5, 55.5), (37.52, 55.5));
+        let mut library = library(&[]);
+
+        assert_eq!(library.insert(std::slice::from_ref(&ride)).unwrap(), 1);
+        assert_eq!(library.insert(&[ride.clone(), run.clone(), run.clone()]).unwrap(), 1);
+        assert_eq!(library.ids().unwrap(), HashSet::from([ride.id, run.id]));
+    }
+
+    #[test]
+    fn query_filters() {
+        let library = library(&[
+            entry("c0", (2023, 7, 1), Activity::Cycling, (10.0, 50.0), (10.1, 50.0)),
+            entry("a0", (2023, 6, 10), Activity::Cycling, (37.0, 55.0), (38.0, 55.0)),
+            entry("b0", (2023, 6, 20), Activity::Running, (37.5, 55.5), (37.52, 55.5)),
+        ]);
+        let query = |filter: Filter| library.query(&filter).unwrap();
+
+        assert_eq!(paths(&query(Filter::default())), vec!("a0.gpx", "b0.gpx", "c0.gpx"));
+        assert_eq!(paths(&query(Filter { from: parse_date("2023-06-20"), ..Filter::default() })), vec!("b0.gpx", "c0.gpx"));
+        assert_eq!(paths(&query(Filter { to: parse_date("2023-06-20"), ..Filter::default() })), vec!("a0.gpx", "b0.gpx"));
+        assert_eq!(paths(&query(Filter { activity: Some(Activity::Running), ..Filter::default() })), vec!("b0.gpx"));
+        assert_eq!(paths(&query(Filter { min_distance: Some(5000.0), ..Filter::default() })), vec!("a0.gpx", "c0.gpx"));
+        assert_eq!(paths(&query(Filter { max_distance: Some(10_000.0), ..Filter::default() })), vec!("b0.gpx", "c0.gpx"));
+        assert_eq!(paths(&query(Filter { area: "37.4,54.9,37.6,55.6".parse().ok(), ..Filter::default() })), vec!("a0.gpx", "b0.gpx"));
+    }
+
+    #[test]
+    fn area_follows_path_segments() {
+        // Диагональ через область, в которой нет ни одной точки упрощенного пути
+        let library = library(&[entry("d0", (2023, 6, 10), Activity::Cycling, (37.0, 55.0), (38.0, 56.0))]);
+        let crossed: Area = "37.2,55.2,37.3,55.3".parse().unwrap();
+        let corner: Area = "37.0,55.8,37.2,56.0".parse().unwrap();
+        let stored = library.query(&Filter::default()).unwrap();
+        assert!(!stored[0].ways.iter().flatten().any(|p| crossed.contains(p)));
+        assert_eq!(library.query(&Filter { area: Some(crossed), ..Filter::default() }).unwrap().len(), 1);
+        assert!(library.query(&Filter { area: Some(corner), ..Filter::default() }).unwrap().is_empty());
+
+        let p = |lon: f64, lat: f64| Waypoint::new(GeoPoint::new(lon, lat));
+        assert!(crossed.crosses(&p(37.25, 55.0), &p(37.25, 56.0)));
+        assert!(!crossed.crosses(&p(37.0, 55.0), &p(37.1, 55.1)));
+        assert!(crossed.touches(&[p(37.25, 55.25)]));
+        assert!(!crossed.touches(&[p(37.0, 55.0)]));
+    }
+
+    #[test]
+    fn areas_are_parsed() {
+        assert_eq!("37.3, 55.5, 37.9, 55.9".parse::<Area>().unwrap(), Area { west: 37.3, south: 55.5, east: 37.9, north: 55.9 });
+        assert!("37.9,55.5,37.3,55.9".parse::<Area>().is_err());
+        assert!("179,0,-179,1".parse::<Area>().is_err());
+        assert!("37.3,55.5,37.9".parse::<Area>().is_err());
+    }
+
+    #[test]
+    fn entries_by_id_prefix() {
+        let library = library(&[
+            entry("abc123", (2023, 6, 10), Activity::Cycling, (37.0, 55.0), (38.0, 55.0)),
+            entry("abd456", (2023, 6, 20), Activity::Cycling, (37.0, 55.0), (38.0, 55.0)),
+        ]);
+
+        assert_eq!(library.get("abc123").unwrap().path, "abc123.gpx");
+        assert_eq!(library.get(" ABD").unwrap().path, "abd456.gpx");
+        assert!(matches!(library.get("ab"), Err(Error::AmbiguousId(_))));
+        assert!(matches!(library.get("abe"), Err(Error::NotInLibrary(_))));
+        assert!(matches!(library.get("xyz"), Err(Error::NotInLibrary(_))));
+        assert!(matches!(library.get(""), Err(Error::NotInLibrary(_))));
+    }
+
+    #[test]
+    fn short_ids() {
+        let long = entry(&content_id(b"ride"), (2023, 6, 10), Activity::Cycling, (37.0, 55.0), (38.0, 55.0));
+        let short = entry("abc", (2023, 6, 10), Activity::Cycling, (37.0, 55.0), (38.0, 55.0));
+
+        assert_eq!(long.short_id(), &long.id[..SHORT_ID]);
+        assert_eq!(short.short_id(), "abc");
+        assert!(to_list(&[short], Lang::En, Units::Metric).contains("\nabc | "));
+    }
+}
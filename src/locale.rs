@@ -0,0 +1,496 @@
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+
+use phf::phf_map;
+
+use crate::activity::Activity;
+use crate::error::Error;
+
+
+/// Язык вывода
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Lang {
+    #[default]
+    En,
+    Ru,
+}
+
+/// Каталог сообщений одного языка. Шаблоны содержат "{}" на местах
+/// подставляемых значений, см. `fill`
+pub struct Messages {
+    pub unknown: &'static str,
+    pub activities: [&'static str; 8], // В порядке вариантов Activity
+
+    // Единицы измерения, названия единиц расстояния, высоты
+    // и скорости - в порядке вариантов Units
+    pub distance_units: [&'static str; 3],
+    pub elevation_units: [&'static str; 3],
+    pub speed_units: [&'static str; 3],
+    pub min: &'static str,
+    pub s: &'static str,
+
+    // Текстовый отчет
+    pub track: &'static str,
+    pub date_utc: &'static str,
+    pub activity: &'static str,
+    pub length: &'static str,
+    pub gps_density: &'static str,
+    pub device: &'static str,
+    pub segment: &'static str,
+    pub time: &'static str,
+    pub total: &'static str,
+    pub moving: &'static str,
+    pub speed: &'static str,
+    pub average: &'static str,
+    pub maximum: &'static str,
+    pub unfiltered: &'static str,
+    pub best: &'static str,
+    pub ascent: &'static str,
+    pub ascent_total: &'static str,
+    pub ascent_max: &'static str,
+    pub descent: &'static str,
+    pub altitude: &'static str,
+    pub altitude_min: &'static str,
+    pub altitude_max: &'static str,
+    pub altitude_start: &'static str,
+    pub altitude_end: &'static str,
+    pub grade: &'static str,
+    pub grade_max_up: &'static str,
+    pub grade_max_down: &'static str,
+    pub grade_avg_up: &'static str,
+    pub grade_avg_down: &'static str,
+    pub grade_bands: &'static str,
+    pub grade_below: &'static str,
+    pub grade_above: &'static str,
+    pub grade_range: &'static str,
+    pub splits: &'static str,
+    pub split_number: &'static str,
+    pub split_distance: &'static str,
+    pub pace: &'static str,
+    pub split_climb: &'static str,
+    pub efforts: &'static str,
+    pub effort_row: &'static str,
+    pub climbs: &'static str,
+    pub climb_row: &'static str,
+    pub uncategorized: &'static str,
+    pub climb_categories: [&'static str; 5], // В порядке вариантов ClimbCategory
+
+    // Подписи штампа
+    pub min_label: &'static str,
+    pub max_label: &'static str,
+    pub activity_short: &'static str,
+    pub total_time: &'static str,
+    pub moving_time: &'static str,
+    pub avg_speed: &'static str,
+    pub max_speed: &'static str,
+    pub avg_pace: &'static str,
+    pub best_pace: &'static str,
+
+    // Таблицы по нескольким файлам
+    pub file: &'static str,
+    pub min_altitude: &'static str,
+    pub max_altitude: &'static str,
+    pub pause_start: &'static str,
+    pub pause_offset: &'static str,
+    pub pause_duration: &'static str,
+
+    // Диалог сохранения
+    pub file_exists: &'static str,
+    pub yes: &'static str,
+    pub saved: &'static str,
+
+    // Пакетная обработка
+    pub batch_failed: &'static str,
+
+    // Итоги по периодам
+    pub periods: [&'static str; 3], // Заголовки итогов в порядке вариантов Period
+    pub period: &'static str,
+    pub count: &'static str,
+    pub longest: &'static str,
+    pub fastest: &'static str,
+    pub summary_total: &'static str,
+    pub undated: &'static str,
+    pub summary_chart: &'static str,
+
+    // Библиотека активностей
+    pub id: &'static str,
+    pub imported: &'static str,
+
+    // Ошибки
+    pub error_io: &'static str,
+    pub error_parse: &'static str,
+    pub error_no_tracks: &'static str,
+    pub error_no_time: &'static str,
+    pub error_no_elevation: &'static str,
+    pub error_short_route: &'static str,
+    pub error_unknown_activity: &'static str,
+    pub error_invalid_effort: &'static str,
+    pub error_invalid_smoothing: &'static str,
+    pub error_unknown_projection: &'static str,
+    pub error_unknown_theme: &'static str,
+    pub error_invalid_theme: &'static str,
+    pub error_unknown_format: &'static str,
+    pub error_unknown_lang: &'static str,
+    pub error_unknown_units: &'static str,
+    pub error_rasterize: &'static str,
+    pub error_pdf: &'static str,
+    pub error_json: &'static str,
+    pub error_invalid_pattern: &'static str,
+    pub error_no_input: &'static str,
+    pub error_unknown_period: &'static str,
+    pub error_database: &'static str,
+    pub error_invalid_area: &'static str,
+    pub error_not_in_library: &'static str,
+    pub error_ambiguous_id: &'static str,
+    pub error_write: &'static str,
+    pub error_unsupported_format: &'static str,
+    pub error_no_stdout: &'static str,
+    pub error_file_exists: &'static str,
+    pub error_overwrite_declined: &'static str,
+}
+
+static CATALOGS: phf::Map<&'static str, Messages> = phf_map! {
+    "en" => Messages {
+        unknown: "Unknown",
+        activities: ["Walking", "Hiking", "Running", "Cycling", "Mountain biking", "Skiing", "Driving", "Swimming"],
+
+        distance_units: ["km", "mi", "nmi"],
+        elevation_units: ["m", "ft", "m"],
+        speed_units: ["km/h", "mph", "kn"],
+        min: "min",
+        s: "s",
+
+        track: "Track",
+        date_utc: "Date (UTC)",
+        activity: "Activity type",
+        length: "Distance",
+        gps_density: "GPS readings per {}",
+        device: "Created by",
+        segment: "Segment #{}",
+        time: "Time",
+        total: "Total",
+        moving: "Moving",
+        speed: "Speed",
+        average: "Average",
+        maximum: "Maximum",
+        unfiltered: "unfiltered",
+        best: "Best",
+        ascent: "Ascent",
+        ascent_total: "Total",
+        ascent_max: "Maximum (continuous)",
+        descent: "Descent",
+        altitude: "Altitude",
+        altitude_min: "Minimum",
+        altitude_max: "Maximum",
+        altitude_start: "At start",
+        altitude_end: "At finish",
+        grade: "Grade",
+        grade_max_up: "Steepest uphill",
+        grade_max_down: "Steepest downhill",
+        grade_avg_up: "Average uphill",
+        grade_avg_down: "Average downhill",
+        grade_bands: "Distance by grade",
+        grade_below: "below {}%",
+        grade_above: "{}% and above",
+        grade_range: "{}% to {}%",
+        splits: "Splits",
+        split_number: "#",
+        split_distance: "Distance",
+        pace: "Pace",
+        split_climb: "Gain/loss",
+        efforts: "Best efforts",
+        effort_row: "{}: {} in {}, {} (points {}-{})",
+        climbs: "Climbs",
+        climb_row: "{}: {} from start, length {}, +{}, grade average {}%, maximum {}%",
+        uncategorized: "Uncategorized",
+        climb_categories: ["Cat 4", "Cat 3", "Cat 2", "Cat 1", "HC"],
+
+        min_label: "min",
+        max_label: "max",
+        activity_short: "Activity",
+        total_time: "Total time",
+        moving_time: "Moving time",
+        avg_speed: "Avg speed",
+        max_speed: "Max speed",
+        avg_pace: "Avg pace",
+        best_pace: "Best pace",
+
+        file: "File",
+        min_altitude: "Min altitude",
+        max_altitude: "Max altitude",
+        pause_start: "Start (UTC)",
+        pause_offset: "From start",
+        pause_duration: "Duration",
+
+        file_exists: "File \"{}\" already exists! Overwrite it? [Y/n]:",
+        yes: "y",
+        saved: "Saved: {}",
+
+        batch_failed: "Failed to process {} of {} files:",
+
+        periods: ["Weekly totals", "Monthly totals", "Yearly totals"],
+        period: "Period",
+        count: "Activities",
+        longest: "Longest",
+        fastest: "Best avg speed",
+        summary_total: "Total",
+        undated: "Activities without date: {}",
+        summary_chart: "Distance, {}",
+
+        id: "ID",
+        imported: "Imported: {}, already in the library: {}",
+
+        error_io: "I/O error: {}",
+        error_parse: "Invalid GPX file: {}",
+        error_no_tracks: "The GPX file contains no tracks!",
+        error_no_time: "The track contains no timestamps!",
+        error_no_elevation: "The track contains no elevation data!",
+        error_short_route: "The route is too short!",
+        error_unknown_activity: "Unknown activity type: {}",
+        error_invalid_effort: "Invalid distance or duration: {}",
+        error_invalid_smoothing: "Unknown smoothing method: {}",
+        error_unknown_projection: "Unknown map projection: {}",
+        error_unknown_theme: "Unknown theme: {}",
+        error_invalid_theme: "Invalid theme: {}",
+        error_unknown_format: "Unknown output format: {}",
+        error_unknown_lang: "Unknown language: {}",
+        error_unknown_units: "Unknown units: {}",
+        error_rasterize: "Failed to render the image: {}",
+        error_pdf: "Failed to build the PDF: {}",
+        error_json: "Failed to build the JSON: {}",
+        error_invalid_pattern: "Invalid file pattern: {}",
+        error_no_input: "No GPX files found: {}",
+        error_unknown_period: "Unknown summary period: {}",
+        error_database: "Library database error: {}",
+        error_invalid_area: "Invalid area, expected west,south,east,north: {}",
+        error_not_in_library: "No activity in the library with ID {}",
+        error_ambiguous_id: "Several activities in the library match ID {}",
+        error_write: "Failed to write the GPX file: {}",
+        error_unsupported_format: "The command does not support output format {}",
+        error_no_stdout: "The command cannot write to standard output",
+        error_file_exists: "File \"{}\" already exists and was not overwritten, use --force to overwrite it",
+        error_overwrite_declined: "Cancelled! File \"{}\" was not overwritten",
+    },
+    "ru" => Messages {
+        unknown: "Неизвестно",
+        activities: ["Ходьба", "Поход", "Бег", "Велосипед", "Горный велосипед", "Лыжи", "Автомобиль", "Плавание"],
+
+        distance_units: ["км", "ми", "м. миля"],
+        elevation_units: ["м", "фт", "м"],
+        speed_units: ["км/ч", "ми/ч", "уз"],
+        min: "мин",
+        s: "с",
+
+        track: "Трек",
+        date_utc: "Дата(UTC)",
+        activity: "Тип активности",
+        length: "Протяженность",
+        gps_density: "GPS-показаний на {}",
+        device: "Создано",
+        segment: "Сегмент №{}",
+        time: "Время",
+        total: "Общее",
+        moving: "Чистое",
+        speed: "Скорость",
+        average: "Средняя",
+        maximum: "Максимальная",
+        unfiltered: "без фильтрации",
+        best: "Лучший",
+        ascent: "Подъем",
+        ascent_total: "Общий",
+        ascent_max: "Максимальный(непрерывный)",
+        descent: "Спуск",
+        altitude: "Высота",
+        altitude_min: "Минимальная",
+        altitude_max: "Максимальная",
+        altitude_start: "На старте",
+        altitude_end: "На финише",
+        grade: "Уклон",
+        grade_max_up: "Максимальный на подъеме",
+        grade_max_down: "Максимальный на спуске",
+        grade_avg_up: "Средний на подъемах",
+        grade_avg_down: "Средний на спусках",
+        grade_bands: "Расстояние по диапазонам",
+        grade_below: "менее {}%",
+        grade_above: "от {}%",
+        grade_range: "от {}% до {}%",
+        splits: "Отрезки",
+        split_number: "№",
+        split_distance: "Дистанция",
+        pace: "Темп",
+        split_climb: "Подъем/спуск",
+        efforts: "Лучшие результаты",
+        effort_row: "{}: {} за {}, {} (точки {}-{})",
+        climbs: "Подъемы",
+        climb_row: "{}: {} от старта, длина {}, +{}, уклон средний {}%, максимальный {}%",
+        uncategorized: "Без категории",
+        climb_categories: ["4 кат.", "3 кат.", "2 кат.", "1 кат.", "ВК"],
+
+        min_label: "мин",
+        max_label: "макс",
+        activity_short: "Активность",
+        total_time: "Общее время",
+        moving_time: "Чистое время",
+        avg_speed: "Средняя скорость",
+        max_speed: "Макс. скорость",
+        avg_pace: "Средний темп",
+        best_pace: "Лучший темп",
+
+        file: "Файл",
+        min_altitude: "Мин. высота",
+        max_altitude: "Макс. высота",
+        pause_start: "Начало(UTC)",
+        pause_offset: "От старта",
+        pause_duration: "Продолжительность",
+
+        file_exists: "Файл \"{}\" уже существует! Заменить его? [Д/н]:",
+        yes: "д",
+        saved: "Сохранено: {}",
+
+        batch_failed: "Не удалось обработать {} из {} файлов:",
+
+        periods: ["Итоги по неделям", "Итоги по месяцам", "Итоги по годам"],
+        period: "Период",
+        count: "Активностей",
+        longest: "Самая длинная",
+        fastest: "Лучшая средняя скорость",
+        summary_total: "Итого",
+        undated: "Активностей без даты: {}",
+        summary_chart: "Протяженность, {}",
+
+        id: "Идентификатор",
+        imported: "Импортировано: {}, уже в библиотеке: {}",
+
+        error_io: "Ошибка ввода-вывода: {}",
+        error_parse: "GPX-файл не корректный: {}",
+        error_no_tracks: "GPX-файл не содержит ни одного трека!",
+        error_no_time: "Трек не содержит временных меток!",
+        error_no_elevation: "Трек не содержит данных о высоте!",
+        error_short_route: "Маршрут слишком короткий!",
+        error_unknown_activity: "Неизвестный тип активности: {}",
+        error_invalid_effort: "Некорректная дистанция или продолжительность: {}",
+        error_invalid_smoothing: "Неизвестный способ сглаживания: {}",
+        error_unknown_projection: "Неизвестная картографическая проекция: {}",
+        error_unknown_theme: "Неизвестная тема оформления: {}",
+        error_invalid_theme: "Тема оформления не корректна: {}",
+        error_unknown_format: "Неизвестный формат вывода: {}",
+        error_unknown_lang: "Неизвестный язык: {}",
+        error_unknown_units: "Неизвестная система единиц: {}",
+        error_rasterize: "Не удалось отрисовать изображение: {}",
+        error_pdf: "Не удалось сформировать PDF: {}",
+        error_json: "Не удалось сформировать JSON: {}",
+        error_invalid_pattern: "Некорректный шаблон имени файла: {}",
+        error_no_input: "GPX-файлы не найдены: {}",
+        error_unknown_period: "Неизвестный период итогов: {}",
+        error_database: "Ошибка базы библиотеки: {}",
+        error_invalid_area: "Некорректная область, ожидается запад,юг,восток,север: {}",
+        error_not_in_library: "В библиотеке нет активности с идентификатором {}",
+        error_ambiguous_id: "Идентификатору {} соответствует несколько активностей в библиотеке",
+        error_write: "Не удалось записать GPX-файл: {}",
+        error_unsupported_format: "Команда не поддерживает формат вывода {}",
+        error_no_stdout: "Команда не может выводить результат в стандартный вывод",
+        error_file_exists: "Файл \"{}\" уже существует и не заменен, для замены укажите --force",
+        error_overwrite_declined: "Отменено! Файл \"{}\" не заменен",
+    },
+};
+
+impl Lang {
+    /// Каталог сообщений языка
+    pub fn messages(&self) -> &'static Messages {
+        CATALOGS.get(self.to_string().as_str()).unwrap()
+    }
+
+    /// Название типа активности на этом языке
+    pub fn activity(&self, activity: Activity) -> &'static str {
+        self.messages().activities[activity as usize]
+    }
+
+    /// Язык из переменных окружения LC_ALL, LC_MESSAGES и LANG,
+    /// в порядке их приоритета. Локали вида "ru_RU.UTF-8" сводятся к языку,
+    /// переменные с неизвестными языками вроде "de_DE.UTF-8", а также
+    /// C и POSIX, не выбирающие язык, пропускаются
+    pub fn from_env() -> Option<Lang> {
+        let values = ["LC_ALL", "LC_MESSAGES", "LANG"].map(|name| env::var(name).ok());
+        from_locales(values.iter().flatten().map(String::as_str))
+    }
+}
+
+// Первый известный язык среди значений переменных локали, по убыванию приоритета
+fn from_locales<'a>(values: impl IntoIterator<Item = &'a str>) -> Option<Lang> {
+    values.into_iter()
+        .filter(|value| {
+            let code = value.trim().split(['.', '@']).next().unwrap_or_default();
+            !code.eq_ignore_ascii_case("c") && !code.eq_ignore_ascii_case("posix")
+        })
+        .find_map(|value| value.parse().ok())
+}
+
+impl fmt::Display for Lang {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Lang::En => write!(f, "en"),
+            Lang::Ru => write!(f, "ru"),
+        }
+    }
+}
+
+impl FromStr for Lang {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = s.trim().to_lowercase();
+        let code = code.split(['_', '-', '.', '@']).next().unwrap_or_default();
+
+        match code {
+            "en" | "c" | "posix" => Ok(Lang::En),
+            "ru" => Ok(Lang::Ru),
+            _ => Err(Error::UnknownLang(s.to_string())),
+        }
+    }
+}
+
+/// Подстановка значений на места "{}" в шаблоне сообщения по порядку
+pub fn fill(template: &str, values: &[&dyn fmt::Display]) -> String {
+    let mut parts = template.split("{}");
+    let mut text = parts.next().unwrap_or_default().to_string();
+
+    for (num, part) in parts.enumerate() {
+        if let Some(value) = values.get(num) {
+            text.push_str(&value.to_string());
+        }
+        text.push_str(part);
+    }
+
+    text
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locale_precedence() {
+        assert_eq!(from_locales(["ru_RU.UTF-8", "en_US.UTF-8", "en_US.UTF-8"]), Some(Lang::Ru));
+        assert_eq!(from_locales(["en_GB.UTF-8", "ru_RU.UTF-8"]), Some(Lang::En));
+        assert_eq!(from_locales(["ru"]), Some(Lang::Ru));
+        assert_eq!(from_locales([]), None);
+    }
+
+    #[test]
+    fn unknown_locales_are_skipped() {
+        assert_eq!(from_locales(["de_DE.UTF-8", "", "ru_RU.UTF-8"]), Some(Lang::Ru));
+        assert_eq!(from_locales(["C", "POSIX", "C.UTF-8", "ru_RU.UTF-8"]), Some(Lang::Ru));
+        assert_eq!(from_locales(["C.UTF-8", "de_DE"]), None);
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(fill("{} of {}", &[&1, &"two"]), "1 of two");
+        assert_eq!(fill("{} of {}", &[&1]), "1 of ");
+        assert_eq!(fill("{}", &[&1, &2]), "1");
+        assert_eq!(fill("no values", &[&1]), "no values");
+        assert_eq!(fill("", &[]), "");
+    }
+}
@@ -1,128 +1,1057 @@
-extern crate gpx;
-
-use std::io::{stdin, stdout, Write};
+use std::io::{stderr, stdin, stdout, ErrorKind, IsTerminal, Write};
 use std::io::BufReader;
-use std::fs::File;
-use std::f64::consts::PI;
-use std::path::Path;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-use clap::Parser;
-use euclid::{Angle, Vector2D};
-use itertools::izip;
+use clap::{Parser, Subcommand};
 use gpx::read;
-use gpx::{Gpx, Waypoint};
+use gpx::Gpx;
+use time::{Date, Duration};
+
+use svg::Document;
+
+use gpx_stamp::{Activity, Area, Effort, Entry, Error, Filter, Lang, Library, Options, Period, Projection, RasterSize, Stamp, Theme, Units,
+                compare_to_text, merge, points_to_csv, segments, simplify, split, summarize, summary_to_json, summary_to_svg,
+                summary_to_text, to_csv, to_geojson, to_json, to_markdown, to_pdf, to_png, write_gpx};
+use gpx_stamp::activity::cadence;
+use gpx_stamp::batch::{expand, process};
+use gpx_stamp::json::batch_to_json;
+use gpx_stamp::library::{content_id, parse_date, to_list};
+use gpx_stamp::locale::fill;
+use gpx_stamp::report::{pages_to_pdf, report_pages};
+use gpx_stamp::stamp::parse_distance;
+use gpx_stamp::stat::Smoothing;
+use gpx_stamp::render::{to_text, segments_to_text, to_card, to_svg, cards_to_svg, Card, Format, RenderOptions};
+
+
+// Имя файла общего отчета по нескольким GPX-файлам, без расширения
+const BATCH_REPORT: &str = "gpx-stamp-report";
 
-use crate::stamp::Stamp;
-use crate::render::{to_text, to_svg};
+// Имя файла диаграммы итогов по периодам, без расширения
+const SUMMARY_REPORT: &str = "gpx-stamp-summary";
 
-pub mod stat;
-pub mod stamp;
-pub mod render;
+// Начало имени файла отчета по активности из библиотеки
+const LIBRARY_REPORT: &str = "gpx-stamp";
+
+// Имя файла объединенного трека
+const MERGE_REPORT: &str = "gpx-stamp-merged.gpx";
+
+// Имя выходного файла, означающее стандартный вывод
+const STDOUT: &str = "-";
 
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
-struct Args {
-    /// Path to GPX-file
-    #[arg()]
-    path: String,
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+
+    #[command(flatten)]
+    global: GlobalArgs,
+}
+
+// Флаги, общие для всех команд
+#[derive(clap::Args, Debug)]
+struct GlobalArgs {
+    /// Output format: text, json, csv, markdown, svg, png, pdf, gpx or geojson.
+    /// Every command accepts only some of them and has its own default
+    #[arg(long, short, global = true)]
+    format: Option<Format>,
+
+    /// Units: metric (km, m, km/h), imperial (mi, ft, mph) or nautical (nmi, m, kn).
+    /// Running, walking and hiking show pace per unit of distance instead of speed
+    #[arg(long, global = true, default_value = "metric")]
+    units: Units,
+
+    /// Output language: en or ru. Defaults to the LC_ALL, LC_MESSAGES or LANG
+    /// environment variables, then to English
+    #[arg(long, global = true)]
+    lang: Option<Lang>,
+
+    /// Output file, or - for standard output. Text formats are printed when omitted,
+    /// images, documents and GPX-files are saved under a name derived from the input
+    #[arg(long, short, global = true)]
+    output: Option<PathBuf>,
+
+    /// Overwrite existing files without asking
+    #[arg(long, global = true, conflicts_with = "no_clobber")]
+    force: bool,
+
+    /// Never overwrite existing files. Without --force and --no-clobber the user
+    /// is asked, or the file is kept when standard input is not a terminal.
+    /// A kept file, including a declined prompt, is reported as an error
+    /// with a non-zero exit status
+    #[arg(long, global = true)]
+    no_clobber: bool,
+}
+
+impl GlobalArgs {
+    fn overwrite(&self) -> Overwrite {
+        overwrite_policy(self.force, self.no_clobber, stdin().is_terminal())
+    }
+}
 
-    /// Text mode
+// Без --force и --no-clobber пользователя спрашивают, только если
+// ввод идет с терминала: в скриптах и по расписанию ответить некому
+fn overwrite_policy(force: bool, no_clobber: bool, terminal: bool) -> Overwrite {
+    if force {
+        Overwrite::Always
+    } else if no_clobber || !terminal {
+        Overwrite::Never
+    } else {
+        Overwrite::Ask
+    }
+}
+
+// Как поступать с уже существующим файлом
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Overwrite {
+    Ask, // Спросить пользователя
+    Always,
+    Never,
+}
+
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Statistics of GPX-files: text (default), json, csv or markdown.
+    /// Files are processed in parallel, files that fail are listed after the report
+    Stats(StatsArgs),
+
+    /// Draw stamps of GPX-files: svg (default), png or pdf. SVG and PNG put the stamps
+    /// of several files in a grid, PDF puts the reports one after another. Saved next
+    /// to the GPX-file, or to gpx-stamp-report.<format> when there are several files
+    Render(RenderArgs),
+
+    /// Side-by-side comparison of two or more GPX-files with differences from
+    /// the first one: text (default), json, csv or markdown
+    Compare(CompareArgs),
+
+    /// Simplify track segments by straightening small turns: gpx (default),
+    /// geojson or csv. Saved to <input>.simplified.<format>
+    Simplify(SimplifyArgs),
+
+    /// Convert a GPX-file: geojson (default), csv with one row per point, or gpx 1.1
+    Convert(ConvertArgs),
+
+    /// Split a GPX-file into one file per track segment, saved as <input>-<number>.gpx
+    /// next to it or into the --output directory
+    Split(SplitArgs),
+
+    /// Merge GPX-files into one, tracks ordered by start time.
+    /// Saved to gpx-stamp-merged.gpx
+    Merge(MergeArgs),
+
+    /// Totals by calendar weeks, months or years: number of activities, distance,
+    /// moving time, ascent, longest activity and best average speed: text (default),
+    /// json, or a bar chart of distance in svg or png saved to gpx-stamp-summary.<format>
+    Summary(SummaryArgs),
+
+    /// Import GPX-files into the activity library. Files already in the library,
+    /// even under another name, are recognized by content and skipped
+    Import(ImportArgs),
+
+    /// List library activities matching the filter
+    List(ListArgs),
+
+    /// Statistics of library activities matching the filter: text (default), json, csv or markdown
+    Query(QueryArgs),
+
+    /// Report of one library activity: text (default), json, svg, png or pdf. Images and
+    /// documents are drawn from the simplified route in the library and saved
+    /// to gpx-stamp-<ID>.<format>
+    Show(ShowArgs),
+}
+
+
+#[derive(clap::Args, Debug)]
+struct InputArgs {
+    /// GPX-files, directories (searched recursively for *.gpx) and glob patterns
+    /// such as "rides/**/*.gpx" (only *.gpx matches are taken)
+    #[arg(required = true)]
+    paths: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct StampArgs {
+    /// Activity type (walking, hiking, running, cycling, mtb, skiing, driving, swimming).
+    /// Detected from every GPX file when omitted
+    #[arg(long)]
+    activity: Option<Activity>,
+
+    /// Split length: metres or a number with unit (m, km, mi, nmi), e.g. 1km, 1mi, 400m.
+    /// Zero disables splits. Defaults to one unit of distance of --units
+    #[arg(long, value_parser = parse_length)]
+    split: Option<f64>,
+
+    /// Distances and durations to search for best efforts, e.g. 1km,5km,1mi,20min,1h
+    #[arg(long, value_delimiter = ',', default_value = "1km,5km,10km,20min,1h")]
+    efforts: Vec<Effort>,
+
+    /// Speed smoothing: none, median, median:<window> or kalman.
+    /// Defaults to the activity profile setting
+    #[arg(long)]
+    smoothing: Option<Smoothing>,
+
+    /// Elevation smoothing window in points (1 disables smoothing).
+    /// Defaults to the activity profile setting
+    #[arg(long)]
+    elevation_window: Option<usize>,
+
+    /// Elevation hysteresis in metres: smaller changes are not counted as gain or loss.
+    /// Defaults to the activity profile setting
+    #[arg(long)]
+    elevation_threshold: Option<f64>,
+}
+
+impl StampArgs {
+    fn options(&self, units: Units) -> Options {
+        Options {
+            activity: self.activity,
+            split: self.split.unwrap_or(units.distance_unit()),
+            efforts: self.efforts.clone(),
+            smoothing: self.smoothing,
+            elevation_window: self.elevation_window,
+            elevation_threshold: self.elevation_threshold,
+            ..Options::default()
+        }
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct DrawArgs {
+    /// PNG resolution in dots per inch
+    #[arg(long, default_value_t = 192.0)]
+    dpi: f64,
+
+    /// PNG width in pixels, overrides --dpi
+    #[arg(long, conflicts_with = "dpi")]
+    width: Option<u32>,
+
+    /// SVG theme: a built-in preset (default, dark, minimal, landscape, square)
+    /// or a path to a TOML theme file
+    #[arg(long, default_value = "default")]
+    theme: String,
+}
+
+impl DrawArgs {
+    fn size(&self) -> RasterSize {
+        self.width.map_or(RasterSize::Dpi(self.dpi), RasterSize::Width)
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct StatsArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    #[command(flatten)]
+    stamp: StampArgs,
+
+    /// Print statistics for every track segment along with the totals
     #[arg(long, default_value_t = false)]
-    svg: bool,
-}
-
-
-// Для некоторых задач достаточно приближенной модели gps-трека, которая
-// содержит лишь часть показаний оригинальных данных. Основная идея данного
-// алгоритма это - выбросить как можно больше точек на относительно прямых участках,
-// которые не сильно влияют на геометрию трека, но при этом сохранить достаточно на изогнутых.
-// Параметр angle_mul отвечает за уровень спрямления выходного трека. Чем он больше,
-// тем больше будут спрямляться неровности. Определяет угол спрямления в диапозоне [0 .. PI / 2]
-// angle_mul = 0.3 => PI/2 * 0.3 = 0.471 радиан(27 градусов)
-fn minimize_way(way: &Vec<Waypoint>, angle_mul: f64) -> Vec<Waypoint> {
-    if way.len() < 6 { return way.clone() };
-
-    let angle_limit = Angle { radians: PI / 2.0 * angle_mul };
-    let prelast = way.len() - 2;
-    let zero_vec = Vector2D::new(0.0, 0.0);
-    let triple_way = izip!(way[0..prelast].iter(),
-                           way[1..prelast].iter(),
-                           way[2..prelast].iter());
-    let mut angle_gup: f64 = angle_limit.get();
-    // Если последовательно применять алгоритм к его же результату, то вторая
-    // точка всегда будет выбрасываться, пока в пути не останутся только две точки.
-    // Такое поведение нам не нужно.
-    // Первая и последняя точки должны обязательно содержаться в результате
-    let mut opt_way: Vec<Waypoint> = vec!(way[0].clone(), way[1].clone());
-
-    for (p1, p2, p3) in triple_way {
-        let v1: Vector2D<f64, ()> = Vector2D::new(
-            p3.point().x() - p1.point().x(),
-            p3.point().y() - p1.point().y()
-        );
-
-        let v2: Vector2D<f64, ()> = Vector2D::new(
-            p3.point().x() - p2.point().x(),
-            p3.point().y() - p2.point().y()
-        );
-
-        if v1 != zero_vec && v2 != zero_vec {
-            let between = v1.angle_to(v2);
-            angle_gup -= between.get().abs();
-
-            if angle_gup <= 0.0 {
-                opt_way.push(p3.clone());
-                angle_gup = angle_limit.get();
-            }
+    segments: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct RenderArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    #[command(flatten)]
+    stamp: StampArgs,
+
+    #[command(flatten)]
+    draw: DrawArgs,
+
+    /// Map projection for the route drawing: mercator, equirectangular or utm
+    #[arg(long, default_value = "mercator")]
+    projection: Projection,
+}
+
+#[derive(clap::Args, Debug)]
+struct CompareArgs {
+    /// GPX-files to compare, the first one is the reference
+    #[arg(required = true, num_args = 2..)]
+    paths: Vec<String>,
+
+    #[command(flatten)]
+    stamp: StampArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct SimplifyArgs {
+    /// GPX-file
+    path: PathBuf,
+
+    /// Largest turn in degrees, from 0 to 90, that is straightened
+    #[arg(long, default_value_t = 12.0)]
+    angle: f64,
+}
+
+#[derive(clap::Args, Debug)]
+struct ConvertArgs {
+    /// GPX-file
+    path: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+struct SplitArgs {
+    /// GPX-file
+    path: PathBuf,
+
+    /// Also split segments where no points were recorded for longer than this,
+    /// e.g. 10min, 1h, 90s
+    #[arg(long, value_parser = parse_gap)]
+    gap: Option<Duration>,
+}
+
+#[derive(clap::Args, Debug)]
+struct MergeArgs {
+    /// GPX-files, directories (searched recursively for *.gpx) and glob patterns
+    /// (only *.gpx matches are taken)
+    #[arg(required = true)]
+    paths: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct SummaryArgs {
+    /// GPX-files, directories (searched recursively for *.gpx) and glob patterns
+    /// (only *.gpx matches are taken)
+    #[arg(required_unless_present = "from_library")]
+    paths: Vec<String>,
+
+    /// Take activities from the library instead of GPX-files
+    #[arg(long, conflicts_with = "paths")]
+    from_library: bool,
+
+    /// Library database file, see import
+    #[arg(long)]
+    library: Option<PathBuf>,
+
+    /// Period: week, month or year. Activities are grouped by their start date in UTC
+    #[arg(long, default_value = "month")]
+    by: Period,
+
+    #[command(flatten)]
+    stamp: StampArgs,
+
+    #[command(flatten)]
+    draw: DrawArgs,
+}
+
+
+#[derive(clap::Args, Debug)]
+struct ImportArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Library database file. Defaults to GPX_STAMP_LIBRARY, then to
+    /// gpx-stamp/library.sqlite in XDG_DATA_HOME or ~/.local/share
+    #[arg(long)]
+    library: Option<PathBuf>,
+
+    #[command(flatten)]
+    stamp: StampArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct FilterArgs {
+    /// First day of the period, YYYY-MM-DD. Activities are matched by their start date in UTC
+    #[arg(long, value_parser = parse_day)]
+    from: Option<Date>,
+
+    /// Last day of the period, YYYY-MM-DD, inclusive
+    #[arg(long, value_parser = parse_day)]
+    to: Option<Date>,
+
+    /// Activity type
+    #[arg(long)]
+    activity: Option<Activity>,
+
+    /// Minimum distance: metres or a number with unit (m, km, mi, nmi)
+    #[arg(long, value_parser = parse_length)]
+    min_distance: Option<f64>,
+
+    /// Maximum distance: metres or a number with unit (m, km, mi, nmi)
+    #[arg(long, value_parser = parse_length)]
+    max_distance: Option<f64>,
+
+    /// Area the route passes through: west,south,east,north in degrees,
+    /// e.g. 37.3,55.5,37.9,55.9. West must not exceed east, so split
+    /// an area across the 180th meridian into two queries
+    #[arg(long, allow_hyphen_values = true)]
+    area: Option<Area>,
+}
+
+impl FilterArgs {
+    fn filter(&self) -> Filter {
+        Filter {
+            from: self.from,
+            to: self.to,
+            activity: self.activity,
+            min_distance: self.min_distance,
+            max_distance: self.max_distance,
+            area: self.area,
+        }
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct ListArgs {
+    #[command(flatten)]
+    filter: FilterArgs,
+
+    /// Library database file, see import
+    #[arg(long)]
+    library: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+struct QueryArgs {
+    #[command(flatten)]
+    filter: FilterArgs,
+
+    /// Library database file, see import
+    #[arg(long)]
+    library: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+struct ShowArgs {
+    /// Activity ID or its first characters, as printed by list
+    id: String,
+
+    /// Print statistics for every track segment along with the totals
+    #[arg(long, default_value_t = false)]
+    segments: bool,
+
+    #[command(flatten)]
+    draw: DrawArgs,
+
+    /// Map projection for the route drawing: mercator, equirectangular or utm
+    #[arg(long, default_value = "mercator")]
+    projection: Projection,
+
+    /// Library database file, see import
+    #[arg(long)]
+    library: Option<PathBuf>,
+}
+
+
+fn parse_length(value: &str) -> Result<f64, String> {
+    parse_distance(value).ok_or(format!("invalid length: {}", value))
+}
+
+fn parse_day(value: &str) -> Result<Date, String> {
+    parse_date(value).ok_or(format!("invalid date, expected YYYY-MM-DD: {}", value))
+}
+
+fn parse_gap(value: &str) -> Result<Duration, String> {
+    match value.parse::<Effort>() {
+        Ok(Effort::Duration(duration)) => Ok(duration),
+        _ => Err(format!("invalid duration: {}", value)),
+    }
+}
+
+fn open_library(path: &Option<PathBuf>) -> Result<Library, Error> {
+    Library::open(&path.clone().unwrap_or_else(Library::default_path))
+}
+
+
+fn read_gpx(path: &Path) -> Result<Gpx, Error> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+
+    Ok(read(reader)?)
+}
+
+fn load(path: &Path, options: &Options) -> Result<(Gpx, Stamp), Error> {
+    let data = fs::read(path)?;
+    let gpx: Gpx = read(data.as_slice())?;
+    let stamp = Stamp::with_cadence(&gpx, options, cadence(&data))?;
+
+    Ok((gpx, stamp))
+}
+
+// Прочитанные файлы со штампами и файлы с ошибками
+type Loaded<'a> = (Vec<(&'a str, Gpx, Stamp)>, Vec<(&'a str, Error)>);
+
+// Параллельное чтение файлов. Для одного файла ошибка возвращается
+// как есть, для нескольких - в списке не прочитанных файлов
+fn load_all<'a>(paths: &[PathBuf], names: &'a [String], options: &Options) -> Result<Loaded<'a>, Error> {
+    let mut loaded: Vec<(&str, Gpx, Stamp)> = vec!();
+    let mut failed: Vec<(&str, Error)> = vec!();
+    for (name, result) in names.iter().zip(process(paths, |path| load(path, options))) {
+        match result {
+            Ok((gpx, stamp)) => loaded.push((name, gpx, stamp)),
+            Err(err) => failed.push((name, err)),
+        }
+    }
+    if paths.len() == 1 {
+        if let Some((_, err)) = failed.pop() {
+            return Err(err);
+        }
+    }
+
+    Ok((loaded, failed))
+}
+
+fn display_names(paths: &[PathBuf]) -> Vec<String> {
+    paths.iter().map(|path| path.display().to_string()).collect()
+}
+
+
+// Имя файла без расширения - название для трека, у которого оно не задано
+fn file_title(path: &str) -> Option<String> {
+    Path::new(path).file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .filter(|stem| stem != "-")
+}
+
+
+// Путь с расширением формата, добавленным к имени файла
+fn with_format(path: &str, format: Format) -> PathBuf {
+    PathBuf::from(format!("{}.{}", path, format))
+}
+
+
+// Статистика всех файлов в одном общем отчете. Файлы, которые не удалось
+// прочитать, перечисляются в сводке ошибок после отчета. Возвращает
+// количество таких файлов
+fn run_stats(args: &StatsArgs, global: &GlobalArgs, lang: Lang) -> Result<usize, Error> {
+    let format = global.format.unwrap_or(Format::Text);
+    let paths = expand(&args.input.paths)?;
+    let names = display_names(&paths);
+    let (loaded, failed) = load_all(&paths, &names, &args.stamp.options(global.units))?;
+    let rows: Vec<(&str, &Stamp)> = loaded.iter().map(|(name, _, stamp)| (*name, stamp)).collect();
+
+    let output = match format {
+        Format::Text => {
+            let reports: Vec<String> = rows.iter()
+                .map(|(name, stamp)| {
+                    let mut report = if paths.len() > 1 { format!("==> {} <==\n", name) } else { String::new() };
+
+                    report.push_str(&to_text(stamp, lang, global.units));
+                    if args.segments {
+                        report.push_str(&format!("\n\n{}", segments_to_text(stamp, lang, global.units)));
+                    }
+                    report
+                })
+                .collect();
+
+            reports.join("\n\n")
+        },
+        Format::Json if paths.len() == 1 => to_json(rows[0].1)?,
+        Format::Json => {
+            let errors: Vec<(&str, String)> = failed.iter().map(|(name, err)| (*name, err.to_string())).collect();
+
+            batch_to_json(&rows, &errors)?
+        },
+        Format::Csv => to_csv(&rows, lang, global.units),
+        Format::Markdown => to_markdown(&rows, lang, global.units),
+        format => return Err(Error::UnsupportedFormat(format.to_string())),
+    };
+    emit(&output, global, lang)?;
+
+    report_failures(&failed, paths.len(), lang);
+
+    Ok(failed.len())
+}
+
+
+// Штампы всех файлов: сетка карточек в SVG и PNG или отчеты друг за другом
+// в PDF. Файлы, которые не удалось прочитать или отрисовать, перечисляются
+// в сводке ошибок
+fn run_render(args: &RenderArgs, global: &GlobalArgs, lang: Lang) -> Result<usize, Error> {
+    let format = global.format.unwrap_or(Format::Svg);
+    if !matches!(format, Format::Svg | Format::Png | Format::Pdf) {
+        return Err(Error::UnsupportedFormat(format.to_string()));
+    }
+
+    let paths = expand(&args.input.paths)?;
+    let single = paths.len() == 1;
+    let names = display_names(&paths);
+    let options = args.stamp.options(global.units);
+    let (loaded, mut failed) = load_all(&paths, &names, &options)?;
+    let render_options = RenderOptions {
+        projection: args.projection,
+        theme: Theme::load(&args.draw.theme)?,
+        lang,
+        units: global.units,
+        elevation_window: args.stamp.elevation_window,
+        grade_section: Some(options.grade_section),
+        title: None,
+    };
+
+    let mut cards: Vec<Card> = vec!();
+    let mut pages: Vec<Document> = vec!();
+    for (name, gpx, stamp) in &loaded {
+        let ways = segments(gpx);
+        let options = RenderOptions { title: file_title(name), ..render_options.clone() };
+        let rendered = if format == Format::Pdf {
+            report_pages(stamp, &ways, &options).map(|report| pages.extend(report))
+        } else {
+            to_card(stamp, &ways, &options).map(|card| cards.push(card))
+        };
+
+        if let Err(err) = rendered {
+            failed.push((name, err));
+        }
+    }
+    if single {
+        if let Some((_, err)) = failed.pop() {
+            return Err(err);
+        }
+    }
+
+    if !cards.is_empty() || !pages.is_empty() {
+        let data = match format {
+            Format::Png => to_png(&cards_to_svg(cards, &render_options.theme), args.draw.size())?,
+            Format::Pdf => {
+                let title = if single { loaded[0].2.header.track.clone().or(file_title(loaded[0].0)) } else { None };
+
+                pages_to_pdf(&pages, title.as_deref())?
+            },
+            _ => cards_to_svg(cards, &render_options.theme).to_string().into_bytes(),
+        };
+        let output = global.output.clone()
+            .unwrap_or_else(|| with_format(if single { &names[0] } else { BATCH_REPORT }, format));
+
+        save(&output, &data, global, lang)?;
+    }
+
+    report_failures(&failed, paths.len(), lang);
+
+    Ok(failed.len())
+}
+
+
+// Сравнение файлов бок о бок. Сравниваются только прочитанные файлы,
+// остальные перечисляются в сводке ошибок
+fn run_compare(args: &CompareArgs, global: &GlobalArgs, lang: Lang) -> Result<usize, Error> {
+    let paths = expand(&args.paths)?;
+    let names = display_names(&paths);
+    let (loaded, failed) = load_all(&paths, &names, &args.stamp.options(global.units))?;
+    let rows: Vec<(&str, &Stamp)> = loaded.iter().map(|(name, _, stamp)| (*name, stamp)).collect();
+
+    let output = match global.format.unwrap_or(Format::Text) {
+        Format::Text => compare_to_text(&rows, lang, global.units),
+        Format::Json => {
+            let errors: Vec<(&str, String)> = failed.iter().map(|(name, err)| (*name, err.to_string())).collect();
+
+            batch_to_json(&rows, &errors)?
+        },
+        Format::Csv => to_csv(&rows, lang, global.units),
+        Format::Markdown => to_markdown(&rows, lang, global.units),
+        format => return Err(Error::UnsupportedFormat(format.to_string())),
+    };
+    emit(&output, global, lang)?;
+
+    report_failures(&failed, paths.len(), lang);
+
+    Ok(failed.len())
+}
+
+
+// Трек в одном из форматов обмена данными
+fn export(gpx: &Gpx, format: Format) -> Result<Vec<u8>, Error> {
+    match format {
+        Format::Gpx => write_gpx(gpx),
+        Format::Geojson => Ok(to_geojson(gpx)?.into_bytes()),
+        Format::Csv => Ok(points_to_csv(gpx).into_bytes()),
+        format => Err(Error::UnsupportedFormat(format.to_string())),
+    }
+}
+
+fn run_simplify(args: &SimplifyArgs, global: &GlobalArgs, lang: Lang) -> Result<usize, Error> {
+    let format = global.format.unwrap_or(Format::Gpx);
+    let data = export(&simplify(&read_gpx(&args.path)?, args.angle), format)?;
+    let output = global.output.clone()
+        .unwrap_or_else(|| args.path.with_extension(format!("simplified.{}", format)));
+
+    save(&output, &data, global, lang)?;
+
+    Ok(0)
+}
+
+fn run_convert(args: &ConvertArgs, global: &GlobalArgs, lang: Lang) -> Result<usize, Error> {
+    let mut data = export(&read_gpx(&args.path)?, global.format.unwrap_or(Format::Geojson))?;
+    if !data.ends_with(b"\n") {
+        data.push(b'\n');
+    }
+
+    save(global.output.as_deref().unwrap_or(Path::new(STDOUT)), &data, global, lang)?;
+
+    Ok(0)
+}
+
+// Части сохраняются рядом с исходным файлом или в каталог --output
+fn run_split(args: &SplitArgs, global: &GlobalArgs, lang: Lang) -> Result<usize, Error> {
+    if let Some(format) = global.format.filter(|format| *format != Format::Gpx) {
+        return Err(Error::UnsupportedFormat(format.to_string()));
+    }
+
+    let parts = split(&read_gpx(&args.path)?, args.gap);
+    let stem = args.path.file_stem().map_or("track".into(), |stem| stem.to_string_lossy());
+    let dir = match &global.output {
+        Some(dir) if is_stdout(dir) => return Err(Error::NoStdout),
+        Some(dir) => {
+            fs::create_dir_all(dir)?;
+            dir.clone()
+        },
+        None => args.path.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+
+    for (num, part) in parts.iter().enumerate() {
+        save(&dir.join(format!("{}-{}.gpx", stem, num + 1)), &write_gpx(part)?, global, lang)?;
+    }
+
+    Ok(0)
+}
+
+fn run_merge(args: &MergeArgs, global: &GlobalArgs, lang: Lang) -> Result<usize, Error> {
+    if let Some(format) = global.format.filter(|format| *format != Format::Gpx) {
+        return Err(Error::UnsupportedFormat(format.to_string()));
+    }
+
+    let paths = expand(&args.paths)?;
+    let files = paths.iter().map(|path| read_gpx(path)).collect::<Result<Vec<Gpx>, Error>>()?;
+    let output = global.output.clone().unwrap_or_else(|| PathBuf::from(MERGE_REPORT));
+
+    save(&output, &write_gpx(&merge(&files))?, global, lang)?;
+
+    Ok(0)
+}
+
+
+// Итоги по периодам для всех файлов. Файлы без даты учитываются
+// только в сводке, не прочитанные файлы перечисляются после итогов
+fn run_summary(args: &SummaryArgs, global: &GlobalArgs, lang: Lang) -> Result<usize, Error> {
+    let format = global.format.unwrap_or(Format::Text);
+    let options = args.stamp.options(global.units);
+    let paths = if args.from_library { vec!() } else { expand(&args.paths)? };
+    let names = display_names(&paths);
+
+    let mut stamps: Vec<Stamp> = vec!();
+    let mut failed: Vec<(&str, Error)> = vec!();
+    if args.from_library {
+        let filter = Filter { activity: args.stamp.activity, ..Filter::default() };
+
+        stamps = open_library(&args.library)?.query(&filter)?.into_iter().map(|entry| entry.stamp).collect();
+    }
+    for (name, result) in names.iter().zip(process(&paths, |path| load(path, &options))) {
+        match result {
+            Ok((_, stamp)) => stamps.push(stamp),
+            Err(err) => failed.push((name, err)),
+        }
+    }
+
+    let stamps: Vec<&Stamp> = stamps.iter().collect();
+    let summary = summarize(&stamps, args.by);
+    match format {
+        Format::Text => emit(&summary_to_text(&summary, lang, global.units), global, lang)?,
+        Format::Json => emit(&summary_to_json(&summary)?, global, lang)?,
+        Format::Svg | Format::Png => {
+            let theme = Theme::load(&args.draw.theme)?;
+            let document = summary_to_svg(&summary, &theme, lang, global.units);
+            let data = if format == Format::Png {
+                to_png(&document, args.draw.size())?
+            } else {
+                document.to_string().into_bytes()
+            };
+            let output = global.output.clone().unwrap_or_else(|| with_format(SUMMARY_REPORT, format));
+
+            save(&output, &data, global, lang)?;
+        },
+        format => return Err(Error::UnsupportedFormat(format.to_string())),
+    }
+
+    report_failures(&failed, paths.len(), lang);
+
+    Ok(failed.len())
+}
+
+
+// Импорт файлов в библиотеку. Файлы, которые уже есть в библиотеке,
+// распознаются по хешу содержимого и повторно не разбираются
+fn run_import(args: &ImportArgs, global: &GlobalArgs, lang: Lang) -> Result<usize, Error> {
+    let mut library = open_library(&args.library)?;
+    let known = library.ids()?;
+    let options = args.stamp.options(global.units);
+    let paths = expand(&args.input.paths)?;
+    let names = display_names(&paths);
+
+    let results = process(&paths, |path| {
+        let data = fs::read(path)?;
+        let id = content_id(&data);
+        if known.contains(&id) {
+            return Ok(None);
         }
 
+        let gpx: Gpx = read(data.as_slice())?;
+        let stamp = Stamp::with_cadence(&gpx, &options, cadence(&data))?;
+
+        Entry::new(id, path.display().to_string(), stamp, &segments(&gpx)).map(Some)
+    });
+
+    let mut entries: Vec<Entry> = vec!();
+    let mut failed: Vec<(&str, Error)> = vec!();
+    for (name, result) in names.iter().zip(results) {
+        match result {
+            Ok(entry) => entries.extend(entry),
+            Err(err) => failed.push((name, err)),
+        }
     }
+    let added = library.insert(&entries)?;
+
+    println!("{}", fill(lang.messages().imported, &[&added, &(paths.len() - failed.len() - added)]));
+    report_failures(&failed, paths.len(), lang);
+
+    Ok(failed.len())
+}
+
+
+fn run_list(args: &ListArgs, global: &GlobalArgs, lang: Lang) -> Result<usize, Error> {
+    if let Some(format) = global.format.filter(|format| *format != Format::Text) {
+        return Err(Error::UnsupportedFormat(format.to_string()));
+    }
+
+    let entries = open_library(&args.library)?.query(&args.filter.filter())?;
+
+    emit(&to_list(&entries, lang, global.units), global, lang)?;
+
+    Ok(0)
+}
+
+
+// Статистика отобранных активностей в тех же форматах, что и для GPX-файлов
+fn run_query(args: &QueryArgs, global: &GlobalArgs, lang: Lang) -> Result<usize, Error> {
+    let entries = open_library(&args.library)?.query(&args.filter.filter())?;
+    let rows: Vec<(&str, &Stamp)> = entries.iter().map(|entry| (entry.path.as_str(), &entry.stamp)).collect();
 
-    opt_way.push(way[prelast + 1].clone());
+    let output = match global.format.unwrap_or(Format::Text) {
+        Format::Text => {
+            let reports: Vec<String> = rows.iter()
+                .map(|(path, stamp)| format!("==> {} <==\n{}", path, to_text(stamp, lang, global.units)))
+                .collect();
+
+            reports.join("\n\n")
+        },
+        Format::Json => batch_to_json(&rows, &[])?,
+        Format::Csv => to_csv(&rows, lang, global.units),
+        Format::Markdown => to_markdown(&rows, lang, global.units),
+        format => return Err(Error::UnsupportedFormat(format.to_string())),
+    };
+    emit(&output, global, lang)?;
 
-    opt_way
+    Ok(0)
 }
 
 
-fn main() {
-    let args = Args::parse();
+// Отчет по активности из библиотеки. Рисунки строятся по упрощенному
+// пути из библиотеки, исходный GPX-файл не нужен
+fn run_show(args: &ShowArgs, global: &GlobalArgs, lang: Lang) -> Result<usize, Error> {
+    let format = global.format.unwrap_or(Format::Text);
+    let entry = open_library(&args.library)?.get(&args.id)?;
+    let stamp = &entry.stamp;
 
+    match format {
+        Format::Text => {
+            let mut report = to_text(stamp, lang, global.units);
+            if args.segments {
+                report.push_str(&format!("\n\n{}", segments_to_text(stamp, lang, global.units)));
+            }
+
+            emit(&report, global, lang)?;
+        },
+        Format::Json => emit(&to_json(stamp)?, global, lang)?,
+        Format::Svg | Format::Png | Format::Pdf => {
+            let render_options = RenderOptions {
+                projection: args.projection,
+                theme: Theme::load(&args.draw.theme)?,
+                lang,
+                units: global.units,
+                elevation_window: None,
+                grade_section: None,
+                title: file_title(&entry.path),
+            };
+            let ways = entry.ways();
+            let data = match format {
+                Format::Png => to_png(&to_svg(stamp, &ways, &render_options)?, args.draw.size())?,
+                Format::Pdf => to_pdf(stamp, &ways, &render_options)?,
+                _ => to_svg(stamp, &ways, &render_options)?.to_string().into_bytes(),
+            };
+            let output = global.output.clone()
+                .unwrap_or_else(|| with_format(&format!("{}-{}", LIBRARY_REPORT, entry.short_id()), format));
 
-    let file = File::open(args.path.clone());
-    if file.is_err() {
-        println!("GPX-файл не корректный или не существует!");
-        return ();
+            save(&output, &data, global, lang)?;
+        },
+        format => return Err(Error::UnsupportedFormat(format.to_string())),
     }
-    let reader = BufReader::new(file.unwrap());
 
-    let gpx: Gpx = read(reader).unwrap();
-    let stamp = Stamp::from(&gpx);
+    Ok(0)
+}
 
-    if !args.svg {
-        print!("{}", to_text(&stamp));
-        return ();
+
+// Сводка ошибок пакетной обработки
+fn report_failures(failed: &[(&str, Error)], total: usize, lang: Lang) {
+    if failed.is_empty() {
+        return;
     }
 
-    let track = &gpx.tracks[0];
-    let way: &Vec<Waypoint> = &track.segments[0].points;
-    let opt_way: Vec<Waypoint> = minimize_way(way, 12.0 / 90.0);
+    eprintln!("{}", fill(lang.messages().batch_failed, &[&failed.len(), &total]));
+    for (name, err) in failed {
+        eprintln!("{}: {}", name, err.localize(lang));
+    }
+}
+
 
-    let svg_path = format!("{}.svg", args.path);
-    if Path::new(&svg_path).exists() {
-        print!("Файл \"{}\" уже существует! Заменить его? [Д/н]:", svg_path);
-        stdout().flush().unwrap();
+fn is_stdout(path: &Path) -> bool {
+    path == Path::new(STDOUT)
+}
 
-        let mut buffer = String::new();
-        stdin().read_line(&mut buffer).unwrap();
+// Текстовый вывод: в файл --output, если он задан, иначе на экран
+fn emit(text: &str, global: &GlobalArgs, lang: Lang) -> Result<(), Error> {
+    let output = global.output.as_deref().unwrap_or(Path::new(STDOUT));
+
+    save(output, format!("{}\n", text).as_bytes(), global, lang)
+}
 
-        match buffer.trim_end() {
-            "Д" => svg::save(svg_path, &to_svg(&stamp, &opt_way)).expect("Не удалось сохранить файл!"),
-            "" => svg::save(svg_path, &to_svg(&stamp, &opt_way)).expect("Не удалось сохранить файл!"),
-            _ => println!("Отменено!"),
+// Можно ли заменить существующий файл. Вопросы и сообщения выводятся
+// в поток ошибок, чтобы не смешиваться с результатом на экране. Если
+// спросить нельзя, файл не заменяется, и это ошибка: скрипт должен
+// узнать, что результат не записан
+// Можно ли заменить существующий файл. Ответ пользователя запрашивается
+// через ask только для Overwrite::Ask, пустой ответ означает согласие
+fn replace(path: &Path, policy: Overwrite, lang: Lang, ask: impl FnOnce() -> Result<String, Error>) -> Result<(), Error> {
+    let text = lang.messages();
+
+    match policy {
+        Overwrite::Always => Ok(()),
+        Overwrite::Never => Err(Error::FileExists(path.display().to_string())),
+        Overwrite::Ask => {
+            let answer = ask()?.trim().to_lowercase();
+
+            if answer.is_empty() || answer == text.yes {
+                Ok(())
+            } else {
+                Err(Error::OverwriteDeclined(path.display().to_string()))
+            }
+        },
+    }
+}
+
+fn prompt(path: &Path, lang: Lang) -> Result<String, Error> {
+    eprint!("{}", fill(lang.messages().file_exists, &[&path.display()]));
+    stderr().flush()?;
+
+    let mut buffer = String::new();
+    stdin().read_line(&mut buffer)?;
+
+    Ok(buffer)
+}
+
+fn save(path: &Path, data: &[u8], global: &GlobalArgs, lang: Lang) -> Result<(), Error> {
+    // Если читатель закрыл канал раньше времени, например head,
+    // оставшийся вывод просто никому не нужен
+    if is_stdout(path) {
+        let mut out = stdout().lock();
+
+        return match out.write_all(data).and_then(|_| out.flush()) {
+            Err(err) if err.kind() == ErrorKind::BrokenPipe => Ok(()),
+            result => Ok(result?),
         };
-    } else {
-        let _ = svg::save(&svg_path, &to_svg(&stamp, &opt_way));
-        println!("Сохранено: {}", &svg_path);
+    }
+    if path.exists() {
+        replace(path, global.overwrite(), lang, || prompt(path, lang))?;
+    }
+
+    fs::write(path, data)?;
+    eprintln!("{}", fill(lang.messages().saved, &[&path.display()]));
+
+    Ok(())
+}
+
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let global = &cli.global;
+    let lang = global.lang.or_else(Lang::from_env).unwrap_or_default();
+
+    let result = match &cli.command {
+        Command::Stats(args) => run_stats(args, global, lang),
+        Command::Render(args) => run_render(args, global, lang),
+        Command::Compare(args) => run_compare(args, global, lang),
+        Command::Simplify(args) => run_simplify(args, global, lang),
+        Command::Convert(args) => run_convert(args, global, lang),
+        Command::Split(args) => run_split(args, global, lang),
+        Command::Merge(args) => run_merge(args, global, lang),
+        Command::Summary(args) => run_summary(args, global, lang),
+        Command::Import(args) => run_import(args, global, lang),
+        Command::List(args) => run_list(args, global, lang),
+        Command::Query(args) => run_query(args, global, lang),
+        Command::Show(args) => run_show(args, global, lang),
     };
+    match result {
+        Ok(0) => ExitCode::SUCCESS,
+        Ok(_) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("{}", err.localize(lang));
+            ExitCode::FAILURE
+        },
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(text: &str) -> impl FnOnce() -> Result<String, Error> + '_ {
+        move || Ok(text.to_string())
+    }
+
+    fn unasked() -> Result<String, Error> {
+        panic!("Пользователя не должны спрашивать")
+    }
+
+    #[test]
+    fn overwrite_precedence() {
+        assert_eq!(overwrite_policy(true, false, true), Overwrite::Always);
+        assert_eq!(overwrite_policy(true, false, false), Overwrite::Always);
+        assert_eq!(overwrite_policy(false, true, true), Overwrite::Never);
+        assert_eq!(overwrite_policy(false, false, false), Overwrite::Never);
+        assert_eq!(overwrite_policy(false, false, true), Overwrite::Ask);
+    }
+
+    #[test]
+    fn force_and_no_clobber_conflict() {
+        assert!(Cli::try_parse_from(["gpx-stamp", "--force", "--no-clobber", "stats", "a.gpx"]).is_err());
+    }
+
+    #[test]
+    fn policies_without_prompt() {
+        let path = Path::new("a.svg");
+
+        assert!(replace(path, Overwrite::Always, Lang::En, unasked).is_ok());
+        assert!(matches!(replace(path, Overwrite::Never, Lang::En, unasked),
+                         Err(Error::FileExists(name)) if name == "a.svg"));
+    }
+
+    #[test]
+    fn prompt_answers() {
+        let path = Path::new("a.svg");
+
+        for text in ["\n", "y\n", "Y\n", " y \r\n"] {
+            assert!(replace(path, Overwrite::Ask, Lang::En, answer(text)).is_ok(), "{text:?}");
+        }
+        assert!(replace(path, Overwrite::Ask, Lang::Ru, answer("Д\n")).is_ok());
+
+        for text in ["n\n", "no\n", "д\n"] {
+            assert!(matches!(replace(path, Overwrite::Ask, Lang::En, answer(text)),
+                             Err(Error::OverwriteDeclined(name)) if name == "a.svg"), "{text:?}");
+        }
+    }
 }
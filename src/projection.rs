@@ -0,0 +1,184 @@
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::Error;
+
+
+// Радиус сферы Web Mercator и параметры эллипсоида WGS 84
+const EARTH_RADIUS: f64 = 6_378_137.0;
+const FLATTENING: f64 = 1.0 / 298.257_223_563;
+const UTM_SCALE: f64 = 0.9996;
+
+/// Картографическая проекция для отрисовки маршрута
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Projection {
+    #[default]
+    Mercator, // Web Mercator
+    Equirectangular, // Равнопромежуточная с поправкой на широту центра маршрута
+    Utm, // Поперечная Меркатора в зоне UTM центра маршрута
+}
+
+impl Projection {
+    /// Перевод долготы и широты в градусах в плоские координаты в метрах.
+    /// center - долгота и широта центра маршрута, от которого зависят
+    /// равнопромежуточная проекция и выбор зоны UTM
+    pub fn project(&self, lon: f64, lat: f64, center: (f64, f64)) -> (f64, f64) {
+        match self {
+            Projection::Mercator => {
+                let lat = lat.clamp(-85.051_129, 85.051_129).to_radians();
+
+                (EARTH_RADIUS * lon.to_radians(), EARTH_RADIUS * (PI / 4.0 + lat / 2.0).tan().ln())
+            },
+            Projection::Equirectangular => {
+                (EARTH_RADIUS * lon.to_radians() * center.1.to_radians().cos(),
+                 EARTH_RADIUS * lat.to_radians())
+            },
+            Projection::Utm => {
+                let zone = ((center.0 + 180.0) / 6.0).floor().clamp(0.0, 59.0);
+                let meridian = zone * 6.0 - 180.0 + 3.0;
+
+                transverse_mercator(lon, lat, meridian)
+            },
+        }
+    }
+}
+
+// Поперечная проекция Меркатора на эллипсоиде WGS 84 (ряды Крюгера).
+// Возвращает смещение на восток от осевого меридиана и на север от экватора
+fn transverse_mercator(lon: f64, lat: f64, meridian: f64) -> (f64, f64) {
+    let n = FLATTENING / (2.0 - FLATTENING);
+    let a = EARTH_RADIUS / (1.0 + n) * (1.0 + n.powi(2) / 4.0 + n.powi(4) / 64.0);
+    let alpha = [
+        n / 2.0 - 2.0 / 3.0 * n.powi(2) + 5.0 / 16.0 * n.powi(3),
+        13.0 / 48.0 * n.powi(2) - 3.0 / 5.0 * n.powi(3),
+        61.0 / 240.0 * n.powi(3),
+    ];
+
+    let lat = lat.to_radians();
+    let dlon = (lon - meridian).to_radians();
+    let e = 2.0 * n.sqrt() / (1.0 + n);
+    let t = (lat.sin().atanh() - e * (e * lat.sin()).atanh()).sinh();
+    let xi = t.atan2(dlon.cos());
+    let eta = (dlon.sin() / (1.0 + t * t).sqrt()).atanh();
+
+    let mut east = eta;
+    let mut north = xi;
+    for (j, alpha) in alpha.iter().enumerate() {
+        let k = 2.0 * (j + 1) as f64;
+
+        east += alpha * (k * xi).cos() * (k * eta).sinh();
+        north += alpha * (k * xi).sin() * (k * eta).cosh();
+    }
+
+    (UTM_SCALE * a * east, UTM_SCALE * a * north)
+}
+
+impl fmt::Display for Projection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Projection::Mercator => write!(f, "mercator"),
+            Projection::Equirectangular => write!(f, "equirectangular"),
+            Projection::Utm => write!(f, "utm"),
+        }
+    }
+}
+
+impl FromStr for Projection {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "mercator" | "web-mercator" => Ok(Projection::Mercator),
+            "equirectangular" | "plate-carree" => Ok(Projection::Equirectangular),
+            "utm" => Ok(Projection::Utm),
+            _ => Err(Error::UnknownProjection(s.to_string())),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POINTS: [(f64, f64); 4] = [(37.6173, 55.7520), (-122.4194, 37.7749), (151.2093, -33.8688), (0.0, 0.0)];
+
+    // Обратная задача для проекции решается методом Ньютона
+    // с численной производной
+    fn unproject(projection: Projection, (x, y): (f64, f64), center: (f64, f64)) -> (f64, f64) {
+        let step = 1e-7;
+        let (mut lon, mut lat) = center;
+
+        for _ in 0..50 {
+            let (px, py) = projection.project(lon, lat, center);
+            let (lx, ly) = projection.project(lon + step, lat, center);
+            let (ax, ay) = projection.project(lon, lat + step, center);
+            let (dxdlon, dydlon) = ((lx - px) / step, (ly - py) / step);
+            let (dxdlat, dydlat) = ((ax - px) / step, (ay - py) / step);
+            let det = dxdlon * dydlat - dxdlat * dydlon;
+
+            lon += ((x - px) * dydlat - (y - py) * dxdlat) / det;
+            lat += ((y - py) * dxdlon - (x - px) * dydlon) / det;
+        }
+
+        (lon, lat)
+    }
+
+    fn assert_round_trip(projection: Projection, (lon, lat): (f64, f64), center: (f64, f64)) {
+        let (back_lon, back_lat) = unproject(projection, projection.project(lon, lat, center), center);
+
+        assert!((back_lon - lon).abs() < 1e-7 && (back_lat - lat).abs() < 1e-7,
+                "{}: ({}, {}) -> ({}, {})", projection, lon, lat, back_lon, back_lat);
+    }
+
+    #[test]
+    fn mercator_round_trip() {
+        for (lon, lat) in POINTS {
+            let (x, y) = Projection::Mercator.project(lon, lat, (0.0, 0.0));
+            let back_lon = (x / EARTH_RADIUS).to_degrees();
+            let back_lat = (2.0 * (y / EARTH_RADIUS).exp().atan() - PI / 2.0).to_degrees();
+
+            assert!((back_lon - lon).abs() < 1e-9 && (back_lat - lat).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn equirectangular_round_trip() {
+        for (lon, lat) in POINTS {
+            let center = (lon + 0.1, lat - 0.1);
+            let (x, y) = Projection::Equirectangular.project(lon, lat, center);
+            let back_lon = (x / EARTH_RADIUS / center.1.to_radians().cos()).to_degrees();
+            let back_lat = (y / EARTH_RADIUS).to_degrees();
+
+            assert!((back_lon - lon).abs() < 1e-9 && (back_lat - lat).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn utm_round_trip() {
+        for (lon, lat) in POINTS {
+            let center = (lon + 0.1, lat - 0.1);
+
+            assert_round_trip(Projection::Utm, (lon, lat), center);
+            assert_round_trip(Projection::Mercator, (lon, lat), center);
+        }
+    }
+
+    #[test]
+    fn utm_zone_meridian() {
+        // Центр в Москве - зона 37 с осевым меридианом 39°
+        let center = (37.6173, 55.7520);
+        let (east, north) = Projection::Utm.project(39.0, 0.0, center);
+        assert!(east.abs() < 1e-6 && north.abs() < 1e-6);
+
+        // Длина дуги меридиана от экватора до 1° на эллипсоиде WGS 84 - 110574.389 м
+        let (east, north) = Projection::Utm.project(39.0, 1.0, center);
+        assert!(east.abs() < 1e-6);
+        assert!((north - UTM_SCALE * 110_574.389).abs() < 0.01, "{}", north);
+
+        let (west, _) = Projection::Utm.project(38.0, 55.0, center);
+        let (east, _) = Projection::Utm.project(40.0, 55.0, center);
+        assert!((west + east).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,55 @@
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::{Options, Tree};
+use svg::Document;
+
+use crate::error::Error;
+
+
+// Шрифт, встроенный в программу, чтобы результат не зависел от
+// установленных в системе шрифтов
+static FONT: &[u8] = include_bytes!("../fonts/DejaVuSans.ttf");
+const FONT_FAMILY: &str = "DejaVu Sans";
+
+// Разрешение, при котором единица SVG соответствует одному пикселю
+const SVG_DPI: f64 = 96.0;
+
+/// Размер растрового изображения
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RasterSize {
+    Dpi(f64), // Разрешение, точек на дюйм
+    Width(u32), // Ширина в пикселях, высота пропорциональна
+}
+
+// Разбор SVG-документа со встроенным шрифтом вместо любого
+// семейства шрифтов, заданного в документе
+pub(crate) fn parse_svg(document: &Document) -> Result<Tree, Error> {
+    let mut options = Options {
+        font_family: FONT_FAMILY.to_string(),
+        ..Options::default()
+    };
+    let fontdb = options.fontdb_mut();
+    fontdb.load_font_data(FONT.to_vec());
+    fontdb.set_sans_serif_family(FONT_FAMILY);
+    fontdb.set_serif_family(FONT_FAMILY);
+    fontdb.set_monospace_family(FONT_FAMILY);
+
+    Tree::from_str(&document.to_string(), &options).map_err(|err| Error::Rasterize(err.to_string()))
+}
+
+/// Отрисовка SVG-документа в PNG встроенным шрифтом
+pub fn to_png(document: &Document, size: RasterSize) -> Result<Vec<u8>, Error> {
+    let tree = parse_svg(document)?;
+
+    let scale = match size {
+        RasterSize::Dpi(dpi) => dpi / SVG_DPI,
+        RasterSize::Width(width) => width as f64 / tree.size().width() as f64,
+    } as f32;
+    let raster_size = tree.size().to_int_size().scale_by(scale)
+        .ok_or(Error::Rasterize(format!("{:?}", size)))?;
+
+    let mut pixmap = Pixmap::new(raster_size.width(), raster_size.height())
+        .ok_or(Error::Rasterize(format!("{}x{}", raster_size.width(), raster_size.height())))?;
+    resvg::render(&tree, Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+
+    pixmap.encode_png().map_err(|err| Error::Rasterize(err.to_string()))
+}
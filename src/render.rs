@@ -1,18 +1,76 @@
+use std::fmt;
+use std::str::FromStr;
+
 use gpx::Waypoint;
 use svg::node::Text as NodeText;
-use time::Duration;
+use time::{Duration, OffsetDateTime};
 use time::format_description::well_known::Iso8601;
 use svg::Document;
-use svg::node::element::{Line, Path, Rectangle, Text};
+use svg::node::element::{Group, Line, Path, Rectangle, Text};
 use svg::node::element::path::{Data, Command, Parameters, Position, Number};
 
-use crate::stamp::Stamp;
+use crate::error::Error;
+use crate::projection::Projection;
+use crate::theme::{Colors, Orientation, Panel, Theme};
+use crate::units::Units;
+use crate::simplify::minimize_way;
+use crate::stat::{points_distance, smooth_elevations, way_grade_sections, grade_band, ElevationFilter, GRADE_BANDS};
+use crate::locale::{fill, Lang, Messages};
+use crate::stamp::{BestEffort, Climb, Effort, Gradient, Options, Pause, Stamp, Split, Timing, Velocity, Elevation};
+
+
+/// Формат вывода штампа, итогов или преобразованного трека
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Format {
+    #[default]
+    Text,
+    Svg,
+    Png,
+    Pdf,
+    Json,
+    Csv,
+    Markdown,
+    Gpx,
+    Geojson,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Format::Text => write!(f, "text"),
+            Format::Svg => write!(f, "svg"),
+            Format::Png => write!(f, "png"),
+            Format::Pdf => write!(f, "pdf"),
+            Format::Json => write!(f, "json"),
+            Format::Csv => write!(f, "csv"),
+            Format::Markdown => write!(f, "markdown"),
+            Format::Gpx => write!(f, "gpx"),
+            Format::Geojson => write!(f, "geojson"),
+        }
+    }
+}
 
+impl FromStr for Format {
+    type Err = Error;
 
-const UNKNOWN_LABEL: &str = "Неизвестно";
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "text" | "txt" => Ok(Format::Text),
+            "svg" => Ok(Format::Svg),
+            "png" => Ok(Format::Png),
+            "pdf" => Ok(Format::Pdf),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "markdown" | "md" => Ok(Format::Markdown),
+            "gpx" => Ok(Format::Gpx),
+            "geojson" => Ok(Format::Geojson),
+            _ => Err(Error::UnknownFormat(s.to_string())),
+        }
+    }
+}
 
 
-fn format_duration(dur: Duration) -> String {
+pub(crate) fn format_duration(dur: Duration) -> String {
     let hours = dur.whole_hours();
     let minutes = dur.whole_minutes() - (hours * 60);
     let seconds = dur.whole_seconds() - (dur.whole_minutes() * 60);
@@ -20,84 +78,346 @@ fn format_duration(dur: Duration) -> String {
     format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
 }
 
-pub fn to_text(stamp: &Stamp) -> String {
-    let unknown = UNKNOWN_LABEL.to_string();
+pub(crate) fn format_distance(meters: f64, text: &Messages, units: Units) -> String {
+    format!("{:.2} {}", units.distance(meters), text.distance_units[units.index()])
+}
 
-    let head = &stamp.header;
-    let mut date = unknown.clone();
-    if head.date.is_some() {
-        date = head.date.unwrap().format(&Iso8601::DEFAULT).unwrap()
-    }
-
-    let head_info = format!("Трек: {} \
-                             \nДата(UTC): {} \
-                             \nТип активности: {} \
-                             \nПротяженность: {:.2} км \
-                             \nGPS-показаний на км: {} \
-                             \nСоздано: {}",
-                            head.track.clone().unwrap_or(unknown.clone()),
-                            date,
-                            head.activity.to_string(),
-                            head.length as f64 / 1000.0,
-                            head.gps_density,
-                            head.device.clone().unwrap_or(unknown.clone())
-    );
+// Скорость хранится в метрах/час
+pub(crate) fn format_speed(speed: f64, text: &Messages, units: Units) -> String {
+    format!("{:.2} {}", units.speed(speed), text.speed_units[units.index()])
+}
+
+pub(crate) fn format_elevation(meters: f64, text: &Messages, units: Units) -> String {
+    format!("{:.0} {}", units.elevation(meters), text.elevation_units[units.index()])
+}
+
+// Темп - время прохождения единицы расстояния при скорости в метрах/час
+fn format_pace(speed: f64, text: &Messages, units: Units) -> String {
+    match units.pace(speed) {
+        Some(secs) => {
+            let secs = secs.round() as i64;
+
+            format!("{:02}:{:02} /{}", secs / 60, secs % 60, text.distance_units[units.index()])
+        },
+        None => text.unknown.to_string(),
+    }
+}
+
+fn format_effort(effort: Effort, text: &Messages, units: Units) -> String {
+    match effort {
+        Effort::Distance(meters) => format_distance(meters as f64, text, units),
+        Effort::Duration(duration) if duration.whole_seconds() % 60 == 0 => {
+            format!("{} {}", duration.whole_minutes(), text.min)
+        },
+        Effort::Duration(duration) => format!("{} {}", duration.whole_seconds(), text.s),
+    }
+}
+
+fn timing_info(time: &Option<Timing>, text: &Messages) -> String {
+    let unknown = text.unknown.to_string();
 
-    let time = &stamp.timing;
     let mut total_dur = unknown.clone();
     let mut pure_dur = unknown.clone();
-    if time.is_some() {
-        total_dur = format_duration(time.unwrap().total);
-        pure_dur = format_duration(time.unwrap().pure);
+    if let Some(time) = time {
+        total_dur = format_duration(time.total);
+        pure_dur = format_duration(time.pure);
     }
 
-    let time_info = format!("\nВремя: \
-                             \nОбщее: {} \
-                             \nЧистое: {}",
-                            total_dur,
-                            pure_dur
-    );
+    format!("\n{}: \
+             \n{}: {} \
+             \n{}: {}",
+            text.time,
+            text.total, total_dur,
+            text.moving, pure_dur
+    )
+}
+
+// Скорость, а для активностей, где принято считать темп, и темп
+fn velocity_info(velo: &Option<Velocity>, pace: bool, text: &Messages, units: Units) -> String {
+    let unknown = text.unknown.to_string();
 
-    let velo = &stamp.velocity;
     let mut avg_speed = unknown.clone();
     let mut max_speed = unknown.clone();
-    if velo.is_some() {
-        avg_speed = format!("{:.2}", velo.unwrap().average as f64 / 1000.0);
-        max_speed = format!("{:.2}", velo.unwrap().maximum as f64 / 1000.0);
-    }
-    let velo_info = format!("\nСкорость: \
-                             \nСредняя: {} км/ч \
-                             \nМаксимальная: {} км/ч",
-                            avg_speed,
-                            max_speed
+    let mut raw_avg_speed = unknown.clone();
+    let mut raw_max_speed = unknown.clone();
+    let mut avg_pace = unknown.clone();
+    let mut best_pace = unknown.clone();
+    if let Some(velo) = velo {
+        avg_speed = format_speed(velo.average as f64, text, units);
+        max_speed = format_speed(velo.maximum as f64, text, units);
+        raw_avg_speed = format_speed(velo.raw_average as f64, text, units);
+        raw_max_speed = format_speed(velo.raw_maximum as f64, text, units);
+        avg_pace = format_pace(velo.average as f64, text, units);
+        best_pace = format_pace(velo.maximum as f64, text, units);
+    }
+
+    let speed = format!("\n{}: \
+                         \n{}: {} ({}: {}) \
+                         \n{}: {} ({}: {})",
+                        text.speed,
+                        text.average, avg_speed, text.unfiltered, raw_avg_speed,
+                        text.maximum, max_speed, text.unfiltered, raw_max_speed
     );
+    if !pace {
+        return speed;
+    }
+
+    format!("{}\n\n{}: \
+             \n{}: {} \
+             \n{}: {}",
+            speed,
+            text.pace,
+            text.average, avg_pace,
+            text.best, best_pace
+    )
+}
+
+fn elevation_info(elev: &Option<Elevation>, text: &Messages, units: Units) -> String {
+    let unknown = text.unknown.to_string();
 
-    let elev = &stamp.elevation;
     let mut total_elev = unknown.clone();
     let mut max_elev = unknown.clone();
-    if elev.is_some() {
-        total_elev = elev.unwrap().total.to_string();
-        max_elev = elev.unwrap().maximum.to_string();
-    }
-    let elev_info = format!("\nПодъем: \
-                             \nОбщий: {} м \
-                             \nМаксимальный(непрерывный): {} м",
-                            total_elev,
-                            max_elev
+    let mut total_loss = unknown.clone();
+    let mut altitudes = [unknown.clone(), unknown.clone(), unknown.clone(), unknown.clone()];
+    if let Some(elev) = elev {
+        total_elev = format_elevation(elev.total as f64, text, units);
+        max_elev = format_elevation(elev.maximum as f64, text, units);
+        total_loss = format_elevation(elev.loss as f64, text, units);
+        altitudes = [elev.lowest, elev.highest, elev.start, elev.end]
+            .map(|alt| format_elevation(alt as f64, text, units));
+    }
+    let [lowest, highest, start, end] = altitudes;
+
+    format!("\n{}: \
+             \n{}: {} \
+             \n{}: {} \
+             \n{}: {} \
+             \n\n{}: \
+             \n{}: {} \
+             \n{}: {} \
+             \n{}: {} \
+             \n{}: {}",
+            text.ascent,
+            text.ascent_total, total_elev,
+            text.ascent_max, max_elev,
+            text.descent, total_loss,
+            text.altitude,
+            text.altitude_min, lowest,
+            text.altitude_max, highest,
+            text.altitude_start, start,
+            text.altitude_end, end
+    )
+}
+
+// Темп отрезка считается по чистому времени
+fn split_pace(split: &Split, text: &Messages, units: Units) -> String {
+    match split.timing {
+        Some(time) if time.pure.as_seconds_f64() > 0.0 => {
+            format_pace(split.length as f64 / (time.pure.as_seconds_f64() / 3600.0), text, units)
+        },
+        _ => text.unknown.to_string(),
+    }
+}
+
+// Колонки строки таблицы отрезков: номер, дистанция, общее и
+// чистое время, темп, скорость, набор и сброс высоты
+fn split_columns(num: usize, split: &Split, text: &Messages, units: Units) -> [String; 7] {
+    let unknown = text.unknown.to_string();
+
+    [
+        format!("{}", num),
+        format_distance(split.length as f64, text, units),
+        split.timing.map_or(unknown.clone(), |time| format_duration(time.total)),
+        split.timing.map_or(unknown.clone(), |time| format_duration(time.pure)),
+        split_pace(split, text, units),
+        split.speed.map_or(unknown.clone(), |speed| format_speed(speed as f64, text, units)),
+        match (split.gain, split.loss) {
+            (Some(gain), Some(loss)) => {
+                format!("+{:.0} / -{}", units.elevation(gain as f64), format_elevation(loss as f64, text, units))
+            },
+            _ => unknown.clone(),
+        },
+    ]
+}
+
+fn splits_info(splits: &[Split], text: &Messages, units: Units) -> String {
+    let header = [text.split_number, text.split_distance, text.total, text.moving,
+                  text.pace, text.speed, text.split_climb];
+    let mut rows: Vec<String> = vec!(format!("\n{}: \n{}", text.splits, header.join(" | ")));
+
+    for (num, split) in splits.iter().enumerate() {
+        rows.push(split_columns(num + 1, split, text, units).join(" | "));
+    }
+
+    rows.join("\n")
+}
+
+fn efforts_info(efforts: &[BestEffort], text: &Messages, units: Units) -> String {
+    let mut rows: Vec<String> = vec!(format!("\n{}:", text.efforts));
+
+    for effort in efforts {
+        let hours = effort.duration.as_seconds_f64() / 3600.0;
+
+        rows.push(fill(text.effort_row, &[
+            &format_effort(effort.effort, text, units),
+            &format_distance(effort.distance as f64, text, units),
+            &format_duration(effort.duration),
+            &format_speed(effort.distance as f64 / hours, text, units),
+            &effort.start,
+            &effort.end,
+        ]));
+    }
+
+    rows.join("\n")
+}
+
+fn climbs_info(climbs: &[Climb], text: &Messages, units: Units) -> String {
+    let mut rows: Vec<String> = vec!(format!("\n{}:", text.climbs));
+
+    for climb in climbs {
+        rows.push(fill(text.climb_row, &[
+            &climb.category.map_or(text.uncategorized.to_string(), |cat| text.climb_categories[cat as usize].to_string()),
+            &format_distance(climb.offset as f64, text, units),
+            &format_distance(climb.length as f64, text, units),
+            &format_elevation(climb.gain as f64, text, units),
+            &format!("{:.1}", climb.average_grade),
+            &format!("{:.1}", climb.max_grade),
+        ]));
+    }
+
+    rows.join("\n")
+}
+
+// Подпись диапазона уклона из GRADE_BANDS
+fn grade_band_label(band: usize, text: &Messages) -> String {
+    match band {
+        0 => fill(text.grade_below, &[&GRADE_BANDS[0]]),
+        band if band == GRADE_BANDS.len() => fill(text.grade_above, &[&GRADE_BANDS[band - 1]]),
+        band => fill(text.grade_range, &[&GRADE_BANDS[band - 1], &GRADE_BANDS[band]]),
+    }
+}
+
+fn gradient_info(gradient: &Option<Gradient>, text: &Messages, units: Units) -> String {
+    let gradient = match gradient {
+        Some(gradient) => gradient,
+        None => return format!("\n{}: {}", text.grade, text.unknown),
+    };
+
+    let mut rows: Vec<String> = vec!(format!("\n{}: \
+                                              \n{}: {:.1}% \
+                                              \n{}: {:.1}% \
+                                              \n{}: {:.1}% \
+                                              \n{}: {:.1}% \
+                                              \n{}:",
+                                             text.grade,
+                                             text.grade_max_up, gradient.max,
+                                             text.grade_max_down, gradient.min,
+                                             text.grade_avg_up, gradient.uphill,
+                                             text.grade_avg_down, gradient.downhill,
+                                             text.grade_bands));
+    for (band, length) in gradient.bands.iter().enumerate() {
+        rows.push(format!("{}: {}", grade_band_label(band, text), format_distance(*length as f64, text, units)));
+    }
+
+    rows.join("\n")
+}
+
+/// Текстовый отчет по всей статистике штампа на заданном языке и в заданных
+/// единицах измерения. Темп выводится для активностей, профиль которых это предполагает
+pub fn to_text(stamp: &Stamp, lang: Lang, units: Units) -> String {
+    let text = lang.messages();
+    let unknown = text.unknown.to_string();
+
+    let head = &stamp.header;
+    let mut date = unknown.clone();
+    if let Some(head_date) = head.date {
+        date = head_date.format(&Iso8601::DEFAULT).unwrap()
+    }
+    let pace = head.activity.profile().pace;
+    // Плотность показаний хранится на километр
+    let gps_density = (head.gps_density as f64 * units.distance_unit() / 1000.0).round();
+
+    let head_info = format!("{}: {} \
+                             \n{}: {} \
+                             \n{}: {} \
+                             \n{}: {} \
+                             \n{}: {} \
+                             \n{}: {}",
+                            text.track, head.track.clone().unwrap_or(unknown.clone()),
+                            text.date_utc, date,
+                            text.activity, lang.activity(head.activity),
+                            text.length, format_distance(head.length as f64, text, units),
+                            fill(text.gps_density, &[&text.distance_units[units.index()]]), gps_density,
+                            text.device, head.device.clone().unwrap_or(unknown.clone())
     );
 
-    format!("{}\n{}\n{}\n{}", head_info, time_info, velo_info, elev_info)
+    let mut report = format!("{}\n{}\n{}\n{}\n{}",
+                             head_info,
+                             timing_info(&stamp.timing, text),
+                             velocity_info(&stamp.velocity, pace, text, units),
+                             elevation_info(&stamp.elevation, text, units),
+                             gradient_info(&stamp.gradient, text, units));
+    if !stamp.splits.is_empty() {
+        report = format!("{}\n{}", report, splits_info(&stamp.splits, text, units));
+    }
+    if !stamp.efforts.is_empty() {
+        report = format!("{}\n{}", report, efforts_info(&stamp.efforts, text, units));
+    }
+    if !stamp.climbs.is_empty() {
+        report = format!("{}\n{}", report, climbs_info(&stamp.climbs, text, units));
+    }
+
+    report
+}
+
+/// Статистика по каждому сегменту трека в отдельности на заданном языке
+/// и в заданных единицах измерения
+pub fn segments_to_text(stamp: &Stamp, lang: Lang, units: Units) -> String {
+    let text = lang.messages();
+    let unknown = text.unknown.to_string();
+    let pace = stamp.header.activity.profile().pace;
+    let mut parts: Vec<String> = vec!();
+
+    for (num, segment) in stamp.segments.iter().enumerate() {
+        let head_info = format!("{} \
+                                 \n{}: {} \
+                                 \n{}: {}",
+                                fill(text.segment, &[&(num + 1)]),
+                                text.track, segment.track.clone().unwrap_or(unknown.clone()),
+                                text.length, format_distance(segment.length as f64, text, units)
+        );
+
+        parts.push(format!("{}\n{}\n{}\n{}",
+                           head_info,
+                           timing_info(&segment.timing, text),
+                           velocity_info(&segment.velocity, pace, text, units),
+                           elevation_info(&segment.elevation, text, units)));
+    }
+
+    parts.join("\n\n")
 }
 
-fn border_rect(way: &Vec<Waypoint>) -> Option<(f64, f64, f64, f64)> {
-    let first = &way[0].point();
+// Центр маршрута: средние долгота и широта всех точек
+fn route_center(ways: &[&[Waypoint]]) -> Option<(f64, f64)> {
+    let points: Vec<&Waypoint> = ways.iter().flat_map(|way| way.iter()).collect();
+    if points.is_empty() {
+        return None;
+    }
+    let count = points.len() as f64;
 
-    let (mut maxx, mut minx) = (first.x(), first.x());
-    let (mut maxy, mut miny) = (first.y(), first.y());
+    Some((points.iter().map(|p| p.point().x()).sum::<f64>() / count,
+          points.iter().map(|p| p.point().y()).sum::<f64>() / count))
+}
 
-    for p in way {
-        let x = p.point().x();
-        let y = p.point().y();
+fn border_rect(ways: &[&[Waypoint]], projection: Projection, center: (f64, f64)) -> Option<(f64, f64, f64, f64)> {
+    let first = &ways.iter().find_map(|way| way.first())?.point();
+    let (firstx, firsty) = projection.project(first.x(), first.y(), center);
+
+    let (mut maxx, mut minx) = (firstx, firstx);
+    let (mut maxy, mut miny) = (firsty, firsty);
+
+    for p in ways.iter().flat_map(|way| way.iter()) {
+        let (x, y) = projection.project(p.point().x(), p.point().y(), center);
 
         maxx = if x > maxx { x } else { maxx };
         minx = if x < minx { x } else { minx };
@@ -110,131 +430,653 @@ fn border_rect(way: &Vec<Waypoint>) -> Option<(f64, f64, f64, f64)> {
 }
 
 
-fn svg_route(way: &Vec<Waypoint>, width: f64) -> (Data, f64) {
-    let (maxx, minx, maxy, miny) = border_rect(way).unwrap();
-    let border_width = (maxx - minx).abs();
-    let border_height = (maxy - miny).abs();
-    let scale_factor = width / border_width;
+// Отображение координат маршрута в систему координат рисунка.
+// Маршрут вписывается в прямоугольник width x max_height с сохранением
+// пропорций и выравнивается по центру по горизонтали
+struct RouteFrame {
+    projection: Projection,
+    center: (f64, f64),
+    minx: f64,
+    miny: f64,
+    offset: f64,
+    scale_factor: f64,
+    height: f64,
+}
+
+impl RouteFrame {
+    fn new(ways: &[&[Waypoint]], width: f64, max_height: f64, projection: Projection) -> Result<RouteFrame, Error> {
+        let center = route_center(ways).ok_or(Error::NoTracks)?;
+        let (maxx, minx, maxy, miny) = border_rect(ways, projection, center).ok_or(Error::NoTracks)?;
+        let border_width = (maxx - minx).abs();
+        let border_height = (maxy - miny).abs();
+        let scale_factor = match (border_width > 0.0, border_height > 0.0) {
+            (true, true) => (width / border_width).min(max_height / border_height),
+            (true, false) => width / border_width,
+            (false, true) => max_height / border_height,
+            (false, false) => return Err(Error::ShortRoute),
+        };
+
+        Ok(RouteFrame {
+            projection,
+            center,
+            minx,
+            miny,
+            offset: (width - border_width * scale_factor) / 2.0,
+            scale_factor,
+            height: border_height * scale_factor,
+        })
+    }
 
-    let first = &way[0].point();
-    let mut pipeline: Vec<Command> = vec![
-        Command::Move(
-            Position::Absolute,
-            Parameters::from(vec![((first.x() - minx) * scale_factor) as Number,
-                                  ((first.y() - miny) * scale_factor) as Number]))
-    ];
-    for p in way {
-        let x = ((p.point().x() - minx) * scale_factor) as Number;
-        let y = ((p.point().y() - miny) * scale_factor) as Number;
+    fn position(&self, p: &Waypoint) -> (f64, f64) {
+        let (x, y) = self.projection.project(p.point().x(), p.point().y(), self.center);
 
-        pipeline.push(Command::Line(Position::Absolute,
-                                    Parameters::from(vec![x.clone(), y.clone()])));
+        ((x - self.minx) * self.scale_factor + self.offset,
+         (y - self.miny) * self.scale_factor)
     }
 
-    (Data::from(pipeline), border_height * scale_factor)
+    // Каждый сегмент рисуется отдельной линией, без соединения с соседними
+    fn path_data(&self, ways: &[&[Waypoint]]) -> Data {
+        let mut pipeline: Vec<Command> = vec![];
+
+        for way in ways.iter().filter(|way| !way.is_empty()) {
+            let (first_x, first_y) = self.position(&way[0]);
+
+            pipeline.push(Command::Move(
+                Position::Absolute,
+                Parameters::from(vec![first_x as Number, first_y as Number])));
+            for p in way.iter() {
+                let (x, y) = self.position(p);
+
+                pipeline.push(Command::Line(Position::Absolute,
+                                            Parameters::from(vec![x as Number, y as Number])));
+            }
+        }
+
+        Data::from(pipeline)
+    }
+}
+
+fn svg_route(ways: &[&[Waypoint]], width: f64, max_height: f64, projection: Projection) -> Result<(Data, RouteFrame), Error> {
+    let frame = RouteFrame::new(ways, width, max_height, projection)?;
+
+    Ok((frame.path_data(ways), frame))
+}
+
+// Участки лучших результатов поверх маршрута. Точки участка отбираются
+// по времени, поэтому подходит и упрощенный путь
+fn svg_efforts(efforts: &[BestEffort], ways: &[&[Waypoint]], frame: &RouteFrame, transform: &str, colors: &Colors) -> Group {
+    let mut group = Group::new();
+
+    for (effort, color) in efforts.iter().zip(colors.efforts.iter().cycle()) {
+        let start = match effort.time {
+            Some(time) => time,
+            None => continue,
+        };
+        let finish = start + effort.duration;
+        let spans: Vec<Vec<Waypoint>> = ways.iter()
+            .map(|way| way.iter()
+                 .filter(|p| p.time.map(OffsetDateTime::from).is_some_and(|t| t >= start && t <= finish))
+                 .cloned()
+                 .collect::<Vec<Waypoint>>())
+            .filter(|span| span.len() > 1)
+            .collect();
+        if spans.is_empty() {
+            continue;
+        }
+        let spans: Vec<&[Waypoint]> = spans.iter().map(|span| span.as_slice()).collect();
+
+        group = group.add(Path::new()
+                          .set("stroke", color.as_str())
+                          .set("stroke-width", 2.0)
+                          .set("stroke-opacity", 0.6)
+                          .set("stroke-linecap", "round")
+                          .set("stroke-linejoin", "round")
+                          .set("fill", "none")
+                          .set("transform", transform)
+                          .set("d", frame.path_data(&spans)));
+    }
+
+    group
+}
+
+// Шаг делений оси: наименьший из "круглых" шагов, при котором
+// делений на диапазоне span получается не больше max_ticks
+pub(crate) fn tick_step(span: f64, steps: &[f64], max_ticks: f64) -> f64 {
+    steps.iter()
+        .copied()
+        .find(|step| span / step <= max_ticks)
+        .unwrap_or(steps[steps.len() - 1])
 }
 
-fn svg_elevation(way: &Vec<Waypoint>, width: f64) -> (Data, f64) {
-    let first = &way[0];
-    let (mut max_elev, mut min_elev) = (first.elevation.unwrap(), first.elevation.unwrap());
-    for p in way {
-        max_elev = if p.elevation.unwrap() > max_elev { p.elevation.unwrap() } else { max_elev };
-        min_elev = if p.elevation.unwrap() < min_elev { p.elevation.unwrap() } else { min_elev };
+// Профиль высот по пройденному расстоянию: заливка, раскрашенная по
+// диапазонам уклона, контур, деления расстояния, линии сетки высот и
+// подписи минимальной и максимальной высоты. Профиль строится по всем
+// точкам пути и высотам, сглаженным фильтром filter, а заливка - по тем
+// же участкам длиной section, что и статистика уклона. Высота
+// масштабируется между минимумом и максимумом с отступом.
+// Группа строится в координатах рисунка с левым верхним углом профиля
+// в точке (x, y). Вместе с высотой профиля возвращается пройденное
+// расстояние в метрах, отложенное по его горизонтальной оси
+fn svg_elevation(ways: &[&[Waypoint]], filter: &ElevationFilter, section: f64, x: f64, y: f64, options: &RenderOptions) -> Result<(Group, f64, f64), Error> {
+    let (width, colors) = (options.theme.width, &options.theme.colors);
+    let (text, units) = (options.lang.messages(), options.units);
+    let mut elevations: Vec<f64> = vec!();
+    for way in ways {
+        elevations.extend(smooth_elevations(way, filter.window).ok_or(Error::NoElevation)?);
+    }
+    let first = elevations.first().ok_or(Error::NoTracks)?;
+
+    let (mut max_elev, mut min_elev) = (*first, *first);
+    for &elev in &elevations {
+        max_elev = if elev > max_elev { elev } else { max_elev };
+        min_elev = if elev < min_elev { elev } else { min_elev };
+    }
+
+    // Расстояние считается только внутри сегментов, разрывы между ними
+    // на профиле не отображаются. Участки уклона переводятся в индексы
+    // общей последовательности точек
+    let mut passed: Vec<f64> = vec!();
+    let mut sections: Vec<(usize, usize, f64)> = vec!();
+    for segment in ways {
+        let offset = passed.len();
+        let mut distance = passed.last().copied().unwrap_or(0.0);
+
+        for (num, p) in segment.iter().enumerate() {
+            if num > 0 {
+                distance += points_distance(&segment[num - 1], p);
+            }
+            passed.push(distance);
+        }
+        for (start, end, _, grade) in way_grade_sections(segment, section, filter).ok_or(Error::NoElevation)? {
+            sections.push((offset + start, offset + end, grade));
+        }
+    }
+    let total = passed.last().copied().unwrap_or(0.0);
+    if total <= 0.0 {
+        return Err(Error::ShortRoute);
     }
 
     let height = width;
-    let scale_factor: f64 = height / max_elev;
-    let step: f64 = width / way.len() as f64;
+    let margin = ((max_elev - min_elev) * 0.1).max(5.0);
+    let (lowest, highest) = (min_elev - margin, max_elev + margin);
+    let x_scale = width / total;
+    let y_scale = height / (highest - lowest);
+    let position = |num: usize| -> (f64, f64) {
+        (passed[num] * x_scale, (elevations[num] - lowest) * y_scale)
+    };
 
-    let mut pipeline: Vec<Command> = vec![
-        Command::Move(
-            Position::Absolute,
-            Parameters::from((0.0f64, 0.0f64))
-        )
+    let mut outline = Data::new().move_to((0.0, 0.0));
+    for num in 0..elevations.len() {
+        outline = outline.line_to(position(num));
+    }
+    outline = outline.line_to((width, 0.0)).close();
+
+    let mut fill = Group::new();
+    for (start, end, grade) in sections {
+        let mut area = Data::new().move_to((position(start).0, 0.0));
+        for num in start..=end {
+            area = area.line_to(position(num));
+        }
+
+        fill = fill.add(Path::new()
+                        .set("fill", colors.grades[grade_band(grade)].as_str())
+                        .set("stroke", "none")
+                        .set("d", area.line_to((position(end).0, 0.0)).close()));
+    }
+
+    let graph = Group::new()
+        .set("transform", format!("translate({}, {}), scale(1, -1)", x, y + height))
+        .add(fill)
+        .add(Path::new()
+             .set("stroke", colors.route.as_str())
+             .set("stroke-width", 0.8)
+             .set("stroke-opacity", 1)
+             .set("stroke-linecap", "square")
+             .set("stroke-linejoin", "square")
+             .set("fill", "none")
+             .set("d", outline));
+
+    let mut axes = Group::new()
+        .set("font-size", "0.35em")
+        .set("fill", colors.muted.as_str());
+
+    // Линии сетки высот. Деления "круглые" в единицах высоты
+    let elev_unit = units.elevation_unit();
+    let elev_step = tick_step(units.elevation(highest - lowest), &[5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0], 5.0);
+    let mut level = (units.elevation(lowest) / elev_step).ceil() * elev_step;
+    while level * elev_unit <= highest {
+        let level_y = y + height - (level * elev_unit - lowest) * y_scale;
+
+        axes = axes
+            .add(Line::new()
+                 .set("stroke", colors.grid.as_str())
+                 .set("stroke-width", 0.3)
+                 .set("stroke-dasharray", "2,2")
+                 .set("x1", x)
+                 .set("y1", level_y)
+                 .set("x2", x + width)
+                 .set("y2", level_y))
+            .add(Text::new()
+                 .set("x", x + 1.0)
+                 .set("y", level_y - 1.0)
+                 .add(NodeText::new(format_elevation(level * elev_unit, text, units))));
+        level += elev_step;
+    }
+
+    // Деления расстояния, "круглые" в единицах расстояния
+    let dist_unit = units.distance_unit();
+    let dist_step = tick_step(units.distance(total), &[0.5, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0], 8.0);
+    let mut mark = 0.0;
+    while mark * dist_unit <= total {
+        let mark_x = x + mark * dist_unit * x_scale;
+
+        axes = axes
+            .add(Line::new()
+                 .set("stroke", colors.grid.as_str())
+                 .set("stroke-width", 0.5)
+                 .set("x1", mark_x)
+                 .set("y1", y + height)
+                 .set("x2", mark_x)
+                 .set("y2", y + height + 2.0))
+            .add(Text::new()
+                 .set("x", mark_x)
+                 .set("y", y + height + 7.0)
+                 .set("text-anchor", if mark == 0.0 { "start" } else { "middle" })
+                 .add(NodeText::new(format!("{} {}", mark, text.distance_units[units.index()]))));
+        mark += dist_step;
+    }
+
+    // Подписи минимальной и максимальной высоты. На почти ровном профиле,
+    // с перепадом меньше минимального поля, подписи теснятся у линии,
+    // поэтому сливаются в одну строку над максимумом
+    let min_num = elevations.iter().position(|elev| *elev == min_elev).unwrap_or(0);
+    let max_num = elevations.iter().position(|elev| *elev == max_elev).unwrap_or(0);
+    let label = |label: &str, num: usize| format!("{} {}", label, format_elevation(elevations[num], text, units));
+    let extremes = if max_elev - min_elev < 5.0 {
+        vec!((max_num, format!("{}, {}", label(text.min_label, min_num), label(text.max_label, max_num)), 60.0, -2.0))
+    } else {
+        vec!((min_num, label(text.min_label, min_num), 30.0, 6.0), (max_num, label(text.max_label, max_num), 30.0, -2.0))
+    };
+    for (num, label, label_width, shift) in extremes {
+        let (point_x, point_y) = position(num);
+
+        axes = axes.add(Text::new()
+                        .set("x", x + point_x.clamp(0.0, width - label_width))
+                        .set("y", y + height - point_y + shift)
+                        .set("fill", colors.text.as_str())
+                        .add(NodeText::new(label)));
+    }
+
+    Ok((Group::new().add(graph).add(axes), height, total))
+}
+
+// Подъемы на профиле высот. Положение подъема на профиле определяется
+// долей пройденного до него расстояния от длины length горизонтальной
+// оси профиля, которая тоже не включает разрывы между сегментами
+fn svg_climbs(climbs: &[Climb], length: f64, x: f64, y: f64, height: f64, options: &RenderOptions) -> Group {
+    let width = options.theme.width;
+    let colors = &options.theme.colors;
+    let text = options.lang.messages();
+    let position = |distance: usize| -> f64 {
+        distance as f64 / length.max(1.0) * width
+    };
+
+    let mut group = Group::new();
+    for climb in climbs {
+        let from = position(climb.offset);
+        let to = position(climb.offset + climb.length);
+
+        group = group.add(Rectangle::new()
+                          .set("x", x + from)
+                          .set("y", y)
+                          .set("width", (to - from).max(1.0))
+                          .set("height", height)
+                          .set("fill", colors.climbs.as_str())
+                          .set("fill-opacity", 0.3));
+        if let Some(category) = climb.category {
+            group = group.add(Text::new()
+                              .set("x", x + from + 1.0)
+                              .set("y", y + 7.0)
+                              .set("font-size", "0.4em")
+                              .set("fill", colors.text.as_str())
+                              .add(NodeText::new(text.climb_categories[category as usize])));
+        }
+    }
+
+    group
+}
+
+// Блок сводной статистики под разделительной линией: дата, протяженность,
+// тип активности, подъем, общее и чистое время, средняя и максимальная
+// скорость. Каждая величина выводится подписью над значением, в четыре
+// колонки на ширину width
+fn svg_stats(stamp: &Stamp, x: f64, y: f64, width: f64, colors: &Colors, lang: Lang, units: Units) -> (Group, f64) {
+    let text = lang.messages();
+    let unknown = text.unknown.to_string();
+    let row_height = 20.0f64;
+    let column_width = width / 4.0;
+
+    let date = stamp.header.date.map_or(unknown.clone(), |date| date.date().to_string());
+    // Для активностей, где принято считать темп, он выводится вместо скорости
+    let (avg_label, max_label, speed): (&str, &str, &dyn Fn(usize) -> String) = if stamp.header.activity.profile().pace {
+        (text.avg_pace, text.best_pace, &|value| format_pace(value as f64, text, units))
+    } else {
+        (text.avg_speed, text.max_speed, &|value| format_speed(value as f64, text, units))
+    };
+    let cells = [
+        (text.date_utc, date),
+        (text.length, format_distance(stamp.header.length as f64, text, units)),
+        (text.activity_short, lang.activity(stamp.header.activity).to_string()),
+        (text.ascent, stamp.elevation.map_or(unknown.clone(), |elev| format_elevation(elev.total as f64, text, units))),
+        (text.total_time, stamp.timing.map_or(unknown.clone(), |time| format_duration(time.total))),
+        (text.moving_time, stamp.timing.map_or(unknown.clone(), |time| format_duration(time.pure))),
+        (avg_label, stamp.velocity.map_or(unknown.clone(), |velo| speed(velo.average))),
+        (max_label, stamp.velocity.map_or(unknown.clone(), |velo| speed(velo.maximum))),
     ];
-    let mut step_num = 0;
-    for p in way {
-        let x = step_num as f64 * step;
-        let y = p.elevation.unwrap() as f64 * scale_factor;
 
-        pipeline.push(Command::Line(Position::Absolute,
-                                    Parameters::from((x, y))));
+    let mut group = Group::new().add(svg_divider(x, y, width, colors));
+    for (num, (label, value)) in cells.into_iter().enumerate() {
+        let cell_x = x + column_width * (num % 4) as f64;
+        let cell_y = y + 2.0 + row_height * (num / 4) as f64;
+
+        group = group
+            .add(Text::new()
+                 .set("x", cell_x)
+                 .set("y", cell_y + 6.0)
+                 .set("font-size", "0.35em")
+                 .set("fill", colors.muted.as_str())
+                 .add(NodeText::new(label)))
+            .add(Text::new()
+                 .set("x", cell_x)
+                 .set("y", cell_y + 15.0)
+                 .set("font-size", "0.5em")
+                 .set("fill", colors.text.as_str())
+                 .add(NodeText::new(value)));
+    }
+
+    (group, 2.0 + row_height * 2.0)
+}
 
-        step_num += 1;
+// Таблица под разделительной линией: строка заголовка и строки
+// значений. Положения колонок рассчитаны на ширину 300
+fn svg_table(header: &[&str], columns: &[f64], rows: Vec<Vec<String>>, x: f64, y: f64, width: f64, colors: &Colors) -> (Group, f64) {
+    if rows.is_empty() {
+        return (Group::new(), 0.0);
     }
 
-    pipeline.push(Command::Line(Position::Absolute, Parameters::from((width, 0.0f64))));
-    pipeline.push(Command::Line(Position::Absolute, Parameters::from((0.0f64, 0.0f64))));
+    let row_height = 8.0f64;
+    let rows_count = rows.len();
+    let header: Vec<String> = header.iter().map(|cell| cell.to_string()).collect();
+
+    let mut group = Group::new()
+        .set("font-size", "0.4em")
+        .set("fill", colors.text.as_str())
+        .add(svg_divider(x, y, width, colors));
+    for (row_num, row) in [header].into_iter().chain(rows).enumerate() {
+        let row_y = y + 2.0 + row_height * (row_num + 1) as f64;
 
-    (Data::from(pipeline), width)
+        for (column_x, cell) in columns.iter().zip(row) {
+            group = group.add(Text::new()
+                              .set("x", x + column_x * width / 300.0)
+                              .set("y", row_y)
+                              .add(NodeText::new(cell)));
+        }
+    }
+
+    (group, 2.0 + row_height * (rows_count + 1) as f64)
 }
 
-pub fn to_svg(stamp: &Stamp, way: &Vec<Waypoint>) -> Document {
-    let width = 300.0f64;
-    let padding = 10.0f64;
-    let (way_points, way_height) = svg_route(way, width - padding);
-    let (elev_points, elev_height) = svg_elevation(way, width);
+// Таблица отрезков, нумерация начинается с first.
+// Колонка общего времени опущена ради ширины
+pub(crate) fn svg_splits(splits: &[Split], first: usize, x: f64, y: f64, theme: &Theme, text: &Messages, units: Units) -> (Group, f64) {
+    let header = [text.split_number, text.split_distance, text.moving, text.pace, text.speed, text.split_climb];
+    let columns = [0.0f64, 18.0, 60.0, 108.0, 163.0, 220.0];
 
-    let way_graph = Path::new()
-        .set("stroke", "purple")
-        .set("stroke-width", 0.8)
-        .set("stroke-opacity", 1)
-        .set("stroke-linecap", "round")
-        .set("stroke-linejoin", "round")
-        .set("fill", "none")
-        .set("transform", format!("translate({}, {}), scale(1, -1)", padding * 1.5, way_height + padding * 1.5))
-        .set("d", way_points);
-
-    let elev_graph = Path::new()
-        .set("stroke", "purple")
+    let rows: Vec<Vec<String>> = splits.iter()
+        .enumerate()
+        .map(|(num, split)| {
+            let [n, dist, _, pure, pace, speed, climb] = split_columns(first + num, split, text, units);
+
+            vec!(n, dist, pure, pace, speed, climb)
+        })
+        .collect();
+
+    svg_table(&header, &columns, rows, x, y, theme.width, &theme.colors)
+}
+
+// Таблица остановок, нумерация начинается с first
+pub(crate) fn svg_pauses(pauses: &[Pause], first: usize, x: f64, y: f64, theme: &Theme, text: &Messages, units: Units) -> (Group, f64) {
+    let header = [text.split_number, text.pause_start, text.pause_offset, text.pause_duration];
+    let columns = [0.0f64, 18.0, 110.0, 180.0];
+
+    let rows: Vec<Vec<String>> = pauses.iter()
+        .enumerate()
+        .map(|(num, pause)| vec!(
+            format!("{}", first + num),
+            format!("{} {:02}:{:02}:{:02}", pause.start.date(), pause.start.hour(),
+                    pause.start.minute(), pause.start.second()),
+            format_distance(pause.offset as f64, text, units),
+            format_duration(pause.duration),
+        ))
+        .collect();
+
+    svg_table(&header, &columns, rows, x, y, theme.width, &theme.colors)
+}
+
+fn svg_divider(x: f64, y: f64, width: f64, colors: &Colors) -> Line {
+    Line::new()
+        .set("stroke", colors.grid.as_str())
         .set("stroke-width", 0.8)
-        .set("stroke-opacity", 1)
-        .set("stroke-linecap", "square")
-        .set("stroke-linejoin", "square")
-        .set("fill", "purple")
-        .set("transform", format!("translate({}, {}), scale(1, -1)", padding, way_height + elev_height + padding * 4.0))
-        .set("d", elev_points);
-
-    let document = Document::new()
-        .set("viewBox", (0, 0, width + padding * 2.0, width * 2.5))
-        // Подложка
-        .add(Rectangle::new()
-             .set("width", "100%")
-             .set("height", "100%")
-             .set("fill", "white")
-        )
+        .set("stroke-opacity", 0.7)
+        .set("x1", x)
+        .set("y1", y)
+        .set("x2", x + width)
+        .set("y2", y)
+}
+
+// Схема маршрута на подложке вместе с участками лучших результатов
+fn svg_route_panel(stamp: &Stamp, ways: &[&[Waypoint]], options: &RenderOptions, x: f64, y: f64) -> Result<(Group, f64), Error> {
+    let theme = &options.theme;
+    let padding = theme.padding;
+    // Для схемы достаточно упрощенного пути
+    let ways: Vec<Vec<Waypoint>> = ways.iter().map(|way| minimize_way(way, 12.0 / 90.0)).collect();
+    let ways: Vec<&[Waypoint]> = ways.iter().map(|way| way.as_slice()).collect();
+    let (way_points, way_frame) = svg_route(&ways, theme.width - padding, theme.map_height - padding, options.projection)?;
+    let way_height = way_frame.height;
+
+    let way_transform = format!("translate({}, {}), scale(1, -1)", x + padding * 0.5, y + way_height + padding * 0.5);
+    let efforts_graph = svg_efforts(&stamp.efforts, &ways, &way_frame, &way_transform, &theme.colors);
+
+    let group = Group::new()
         .add(Rectangle::new()
-             .set("x", padding)
-             .set("y", padding)
-             .set("width", width)
+             .set("x", x)
+             .set("y", y)
+             .set("width", theme.width)
              .set("height", way_height + padding)
-             .set("fill", "lavender")
-        )
-        .add(way_graph)
+             .set("fill", theme.colors.panel.as_str()))
+        .add(Path::new()
+             .set("stroke", theme.colors.route.as_str())
+             .set("stroke-width", 0.8)
+             .set("stroke-opacity", 1)
+             .set("stroke-linecap", "round")
+             .set("stroke-linejoin", "round")
+             .set("fill", "none")
+             .set("transform", way_transform)
+             .set("d", way_points))
+        .add(efforts_graph);
+
+    Ok((group, way_height + padding))
+}
+
+// Профиль высот на подложке вместе с подъемами и подписями расстояния под ним
+fn svg_elevation_panel(stamp: &Stamp, ways: &[&[Waypoint]], options: &RenderOptions, x: f64, y: f64) -> Result<(Group, f64), Error> {
+    let theme = &options.theme;
+    let top = y + theme.padding * 0.5;
+    let mut filter = stamp.header.activity.profile().elevation_filter;
+    if let Some(window) = options.elevation_window {
+        filter.window = window;
+    }
+    let section = options.grade_section.unwrap_or(Options::default().grade_section);
+    let (elev_graph, elev_height, length) = svg_elevation(ways, &filter, section, x, top, options)?;
+    let climbs_graph = svg_climbs(&stamp.climbs, length, x, top, elev_height, options);
+
+    let group = Group::new()
         .add(Rectangle::new()
-             .set("x", padding)
-             .set("y", way_height + padding * 3.5)
-             .set("width", width)
-             .set("height", elev_height + padding * 0.5)
-             .set("fill", "lavender")
-        )
-        .add(elev_graph)
+             .set("x", x)
+             .set("y", y)
+             .set("width", theme.width)
+             .set("height", elev_height + theme.padding * 0.5)
+             .set("fill", theme.colors.panel.as_str()))
+        .add(climbs_graph)
+        .add(elev_graph);
+
+    Ok((group, elev_height + theme.padding * 0.5 + 8.0))
+}
+
+// Название трека. У многих устройств его нет, тогда выводится
+// запасное название из настроек, обычно имя файла
+pub(crate) fn svg_title(stamp: &Stamp, x: f64, y: f64, options: &RenderOptions) -> (Group, f64) {
+    let colors = &options.theme.colors;
+    let track_name = stamp.header.track.as_deref()
+        .or(options.title.as_deref())
+        .unwrap_or(options.lang.messages().unknown);
+
+    let group = Group::new()
         .add(Text::new()
-             .set("x", padding)
-             .set("y", padding * 5.5 + way_height + elev_height)
+             .set("x", x)
+             .set("y", y + 9.0)
              .set("font-size", "0.6em")
-             .set("fill", "black")
-             .add(NodeText::new(stamp.header.track.clone().unwrap()))
-        )
-        .add(Line::new()
-             .set("stroke", "grey")
-             .set("stroke-width", 0.8)
-             .set("stroke-opacity", 0.7)
-             .set("x1", padding)
-             .set("y1", padding * 6.5 + way_height + elev_height)
-             .set("x2", width + padding)
-             .set("y2", padding * 6.5 + way_height + elev_height)
-        );
+             .set("fill", colors.text.as_str())
+             .add(NodeText::new(track_name)));
+
+    (group, 11.0)
+}
+
+fn svg_panel(panel: Panel, stamp: &Stamp, ways: &[&[Waypoint]], options: &RenderOptions, x: f64, y: f64) -> Result<(Group, f64), Error> {
+    let theme = &options.theme;
+    let text = options.lang.messages();
+
+    match panel {
+        Panel::Route => svg_route_panel(stamp, ways, options, x, y),
+        Panel::Elevation => svg_elevation_panel(stamp, ways, options, x, y),
+        Panel::Title => Ok(svg_title(stamp, x, y, options)),
+        Panel::Stats => Ok(svg_stats(stamp, x, y, theme.width, &theme.colors, options.lang, options.units)),
+        Panel::Splits => Ok(svg_splits(&stamp.splits, 1, x, y, theme, text, options.units)),
+        Panel::Pauses => Ok(svg_pauses(&stamp.pauses, 1, x, y, theme, text, options.units)),
+    }
+}
+
+/// Настройки отрисовки штампа в SVG
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderOptions {
+    pub projection: Projection, // Картографическая проекция схемы маршрута
+    pub theme: Theme, // Оформление и компоновка панелей
+    pub lang: Lang, // Язык подписей
+    pub units: Units, // Единицы измерения величин
+    pub elevation_window: Option<usize>, // Окно сглаживания высоты на профиле. Если не задано, берется из профиля активности
+    pub grade_section: Option<f64>, // Длина участка уклона на профиле. Должна совпадать с `Options::grade_section` штампа
+    pub title: Option<String>, // Название для трека без названия, например имя файла
+}
 
-    document
+/// Карточка штампа для размещения в SVG-документе
+#[derive(Clone, Debug)]
+pub struct Card {
+    pub group: Group, // Панели карточки в координатах с началом в ее левом верхнем углу
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Карточка штампа: панели схемы маршрута, профиля высот, названия трека,
+/// сводной статистики, таблиц отрезков и остановок в порядке и оформлении, заданных темой.
+/// При вертикальной ориентации панели идут друг под другом, при горизонтальной
+/// и квадратной первая панель занимает левую колонку, остальные - правую.
+/// Высота карточки не меньше ширины, кроме горизонтальной ориентации.
+/// Если в треке нет высоты, панель профиля высот пропускается.
+/// Передаются полные сегменты пути: схема маршрута упрощается с помощью
+/// `minimize_way`, а профиль высот строится по всем точкам
+pub fn to_card(stamp: &Stamp, ways: &[&[Waypoint]], options: &RenderOptions) -> Result<Card, Error> {
+    let theme = &options.theme;
+    let padding = theme.padding;
+
+    let columns: Vec<&[Panel]> = match theme.orientation {
+        Orientation::Portrait => vec!(&theme.panels),
+        Orientation::Landscape | Orientation::Square => {
+            let (first, rest) = theme.panels.split_at(theme.panels.len().min(1));
+
+            vec!(first, rest)
+        },
+    };
+
+    let mut panels = Group::new();
+    let mut bottom = 0.0f64;
+    for (num, column) in columns.iter().enumerate() {
+        let x = padding + (theme.width + padding) * num as f64;
+        let mut y = padding;
+
+        // Без данных о высоте профиль не рисуется, остальные панели полезны и без него
+        for panel in column.iter().filter(|panel| **panel != Panel::Elevation || stamp.elevation.is_some()) {
+            let (group, height) = svg_panel(*panel, stamp, ways, options, x, y)?;
+
+            panels = panels.add(group);
+            if height > 0.0 {
+                y += height + padding;
+            }
+        }
+        bottom = bottom.max(y);
+    }
+
+    let width = padding + (theme.width + padding) * columns.len() as f64;
+    let min_height = match theme.orientation {
+        Orientation::Portrait | Orientation::Square => width,
+        Orientation::Landscape => 0.0,
+    };
+    let height = min_height.max(bottom + padding);
+
+    Ok(Card { group: panels, width, height })
+}
+
+/// Отрисовка штампа в SVG-документ из одной карточки, см. `to_card`
+pub fn to_svg(stamp: &Stamp, ways: &[&[Waypoint]], options: &RenderOptions) -> Result<Document, Error> {
+    Ok(cards_to_svg(vec!(to_card(stamp, ways, options)?), &options.theme))
+}
+
+/// Несколько карточек в одном SVG-документе. Карточки идут по строкам сетки
+/// с примерно равным числом строк и колонок, ширина колонки - ширина
+/// наибольшей карточки, высота строки - высота наибольшей карточки в строке
+pub fn cards_to_svg(cards: Vec<Card>, theme: &Theme) -> Document {
+    let columns = ((cards.len() as f64).sqrt().ceil() as usize).max(1);
+    let cell_width = cards.iter().map(|card| card.width).fold(0.0, f64::max);
+    let row_heights: Vec<f64> = cards.chunks(columns)
+        .map(|row| row.iter().map(|card| card.height).fold(0.0, f64::max))
+        .collect();
+
+    let mut content = Group::new();
+    for (num, card) in cards.into_iter().enumerate() {
+        let x = cell_width * (num % columns) as f64;
+        let y: f64 = row_heights[..num / columns].iter().sum();
+
+        content = content.add(Group::new()
+                              .set("transform", format!("translate({}, {})", x, y))
+                              .add(card.group));
+    }
+
+    let width = cell_width * columns as f64;
+    let height: f64 = row_heights.iter().sum();
+
+    svg_document(theme, width, height, content)
+}
+
+// Документ заданного размера с подложкой и шрифтом темы
+pub(crate) fn svg_document(theme: &Theme, width: f64, height: f64, content: Group) -> Document {
+    Document::new()
+        .set("viewBox", (0.0, 0.0, width, height))
+        .set("font-family", theme.font.family.as_str())
+        .set("font-size", theme.font.size)
+        // Подложка
+        .add(Rectangle::new()
+             .set("width", "100%")
+             .set("height", "100%")
+             .set("fill", theme.colors.background.as_str())
+        )
+        .add(content)
 }
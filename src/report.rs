@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+use gpx::Waypoint;
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, TextStr};
+use svg::Document;
+use svg::node::element::Group;
+use svg2pdf::ConversionOptions;
+
+use crate::error::Error;
+use crate::raster::parse_svg;
+use crate::render::{svg_document, svg_pauses, svg_splits, svg_title, to_svg, RenderOptions};
+use crate::stamp::Stamp;
+use crate::theme::{Orientation, Panel, Theme};
+
+
+// Размер страницы A4 и поля в пунктах
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const PAGE_MARGIN: f32 = 36.0;
+
+// Высота панели названия трека и строки таблицы, как при отрисовке штампа
+const TITLE_HEIGHT: f64 = 11.0;
+const ROW_HEIGHT: f64 = 8.0;
+
+/// Отчет о поездке в PDF: карточка штампа, схема маршрута и профиль высот
+/// на отдельных страницах, затем таблицы отрезков и остановок, разбитые
+/// на страницы. Страницы рисуются теми же панелями, что и штамп в SVG
+pub fn to_pdf(stamp: &Stamp, ways: &[&[Waypoint]], options: &RenderOptions) -> Result<Vec<u8>, Error> {
+    pages_to_pdf(&report_pages(stamp, ways, options)?, stamp.header.track.as_deref().or(options.title.as_deref()))
+}
+
+/// PDF-документ из SVG-страниц, например отчетов нескольких поездок подряд.
+/// Каждая страница вписывается в лист A4
+pub fn pages_to_pdf(pages: &[Document], title: Option<&str>) -> Result<Vec<u8>, Error> {
+    let mut alloc = Ref::new(1);
+    let catalog_id = alloc.bump();
+    let page_tree_id = alloc.bump();
+    let mut page_ids: Vec<Ref> = vec!();
+    let mut pdf = Pdf::new();
+
+    for (num, document) in pages.iter().enumerate() {
+        let tree = parse_svg(document)?;
+        let (chunk, svg_id) = svg2pdf::to_chunk(&tree, ConversionOptions::default())
+            .map_err(|err| Error::Pdf(err.to_string()))?;
+        let mut refs: HashMap<Ref, Ref> = HashMap::new();
+        let chunk = chunk.renumber(|old| *refs.entry(old).or_insert_with(|| alloc.bump()));
+        let svg_id = refs[&svg_id];
+
+        // Страница вписывается в поля и прижимается к верхнему краю
+        let (width, height) = (tree.size().width(), tree.size().height());
+        let scale = ((PAGE_WIDTH - PAGE_MARGIN * 2.0) / width).min((PAGE_HEIGHT - PAGE_MARGIN * 2.0) / height);
+        let (width, height) = (width * scale, height * scale);
+        let (x, y) = ((PAGE_WIDTH - width) / 2.0, PAGE_HEIGHT - PAGE_MARGIN - height);
+
+        let page_id = alloc.bump();
+        let content_id = alloc.bump();
+        let svg_name = format!("S{}", num);
+
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT));
+        page.parent(page_tree_id);
+        page.contents(content_id);
+        page.resources().x_objects().pair(Name(svg_name.as_bytes()), svg_id);
+        page.finish();
+
+        let mut content = Content::new();
+        content.transform([width, 0.0, 0.0, height, x, y]);
+        content.x_object(Name(svg_name.as_bytes()));
+
+        pdf.stream(content_id, &content.finish());
+        pdf.extend(&chunk);
+        page_ids.push(page_id);
+    }
+
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id).kids(page_ids.iter().copied()).count(page_ids.len() as i32);
+    if let Some(title) = title {
+        pdf.document_info(alloc.bump()).title(TextStr(title));
+    }
+
+    Ok(pdf.finish())
+}
+
+/// Страницы отчета о поездке в виде SVG-документов, см. `to_pdf`
+pub fn report_pages(stamp: &Stamp, ways: &[&[Waypoint]], options: &RenderOptions) -> Result<Vec<Document>, Error> {
+    let theme = &options.theme;
+    let text = options.lang.messages();
+    let width = theme.width + theme.padding * 2.0;
+    let page_height = width * (PAGE_HEIGHT / PAGE_WIDTH) as f64;
+    let page = |panels: Vec<Panel>, map_height: f64| RenderOptions {
+        theme: Theme {
+            orientation: Orientation::Portrait,
+            panels,
+            map_height,
+            ..theme.clone()
+        },
+        ..options.clone()
+    };
+
+    // Без данных о высоте профиль не рисуется ни на карточке, ни отдельной страницей
+    let card: Vec<Panel> = theme.panels.iter()
+        .copied()
+        .filter(|panel| !matches!(panel, Panel::Splits | Panel::Pauses))
+        .collect();
+    let map_height = page_height - theme.padding * 4.0 - TITLE_HEIGHT;
+
+    let mut pages: Vec<Document> = vec!(
+        to_svg(stamp, ways, &page(card, theme.map_height))?,
+        to_svg(stamp, ways, &page(vec!(Panel::Title, Panel::Route), map_height))?,
+    );
+    if stamp.elevation.is_some() {
+        pages.push(to_svg(stamp, ways, &page(vec!(Panel::Title, Panel::Elevation), theme.map_height))?);
+    }
+
+    let splits = table_pages(stamp, options, page_height, stamp.splits.len(), |from, to, x, y| {
+        svg_splits(&stamp.splits[from..to], from + 1, x, y, theme, text, options.units)
+    });
+    let pauses = table_pages(stamp, options, page_height, stamp.pauses.len(), |from, to, x, y| {
+        svg_pauses(&stamp.pauses[from..to], from + 1, x, y, theme, text, options.units)
+    });
+    pages.extend(splits);
+    pages.extend(pauses);
+
+    Ok(pages)
+}
+
+// Страницы длинной таблицы из rows строк: название трека и столько
+// строк, сколько помещается на страницу. table рисует строки [from, to)
+fn table_pages<F>(stamp: &Stamp, options: &RenderOptions, page_height: f64, rows: usize, table: F) -> Vec<Document>
+where
+    F: Fn(usize, usize, f64, f64) -> (Group, f64),
+{
+    let theme = &options.theme;
+    let padding = theme.padding;
+    let table_top = padding * 2.0 + TITLE_HEIGHT;
+    let per_page = (((page_height - table_top - padding - 2.0) / ROW_HEIGHT) as usize).saturating_sub(1).max(1);
+
+    let mut pages: Vec<Document> = vec!();
+    for from in (0..rows).step_by(per_page) {
+        let to = (from + per_page).min(rows);
+        let (title, _) = svg_title(stamp, padding, padding, options);
+        let (table, _) = table(from, to, padding, table_top);
+
+        pages.push(svg_document(theme, theme.width + padding * 2.0, page_height, Group::new().add(title).add(table)));
+    }
+
+    pages
+}
@@ -1,49 +1,161 @@
-use std::fmt;
+use std::str::FromStr;
 
-use gpx::{Gpx, Track, Waypoint};
+use gpx::{Gpx, Waypoint};
+use serde::{Deserialize, Serialize};
 use time::{OffsetDateTime, Duration};
 
-use crate::stat::{way_distance, way_durations, max_speed, avg_speed, way_elevations};
+pub use crate::activity::Activity;
+use crate::activity::{Profile, detect, from_gpx_type};
+use crate::error::Error;
+use crate::json;
+use crate::stat::{ways_distance, ways_durations, ways_max_speed, ways_avg_speed, ways_raw_max_speed,
+                  ways_raw_avg_speed, ways_elevations, ways_altitudes, ways_splits, ways_best_distance,
+                  ways_best_duration, ways_climbs, ways_grades, ways_pauses, grade_band, ClimbRule, Smoothing, GRADE_BANDS};
 
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub enum Activity {
-    Cycling,
-    Running
+/// Все непустые сегменты всех треков GPX-файла в порядке следования
+pub fn segments(gpx: &Gpx) -> Vec<&[Waypoint]> {
+    gpx.tracks.iter()
+        .flat_map(|track| track.segments.iter())
+        .map(|segment| segment.points.as_slice())
+        .filter(|way| !way.is_empty())
+        .collect()
 }
 
-impl fmt::Display for Activity {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", self)
+/// Параметры построения штампа
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    pub activity: Option<Activity>, // Тип активности. Если не задан, определяется автоматически
+    pub split: f64, // Длина отрезка разбивки в метрах. Ноль отключает разбивку
+    pub efforts: Vec<Effort>, // Дистанции и продолжительности для поиска лучших результатов
+    pub smoothing: Option<Smoothing>, // Сглаживание скорости. Если не задано, берется из профиля активности
+    pub elevation_window: Option<usize>, // Окно сглаживания высоты. Если не задано, берется из профиля
+    pub elevation_threshold: Option<f64>, // Гистерезис высоты в метрах. Если не задан, берется из профиля
+    pub climb_rule: ClimbRule, // Правило выделения подъемов
+    pub grade_section: f64, // Длина участка в метрах для подсчета уклона
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            activity: None,
+            split: 1000.0,
+            efforts: vec!(
+                Effort::Distance(1000),
+                Effort::Distance(5000),
+                Effort::Distance(10_000),
+                Effort::Duration(Duration::minutes(20)),
+                Effort::Duration(Duration::HOUR),
+            ),
+            smoothing: None,
+            elevation_window: None,
+            elevation_threshold: None,
+            climb_rule: ClimbRule::default(),
+            grade_section: 100.0,
+        }
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+/// Разбор дистанции вида "400", "400m", "1.5km", "1mi" или "1nmi" в метры
+pub fn parse_distance(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let (number, multiplier) = if let Some(number) = value.strip_suffix("km") {
+        (number, 1000.0)
+    } else if let Some(number) = value.strip_suffix("nmi") {
+        (number, 1852.0)
+    } else if let Some(number) = value.strip_suffix("mi") {
+        (number, 1609.344)
+    } else if let Some(number) = value.strip_suffix('m') {
+        (number, 1.0)
+    } else {
+        (value, 1.0)
+    };
+
+    number.trim().parse::<f64>().ok().filter(|length| *length >= 0.0).map(|length| length * multiplier)
+}
+
+/// Сводная статистика по всем трекам GPX-файла
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Stamp {
     pub header: Header,
     pub timing: Option<Timing>,
     pub velocity: Option<Velocity>,
     pub elevation: Option<Elevation>,
+    pub segments: Vec<Segment>, // Статистика по каждому сегменту в отдельности
+    pub splits: Vec<Split>, // Разбивка пути на отрезки равной длины
+    pub efforts: Vec<BestEffort>, // Лучшие результаты на заданных дистанциях и отрезках времени
+    pub climbs: Vec<Climb>, // Найденные подъемы в порядке следования
+    pub gradient: Option<Gradient>,
+    pub pauses: Vec<Pause>, // Остановки и разрывы между сегментами
 }
 
+impl Stamp {
+    pub fn new(gpx: &Gpx, options: &Options) -> Result<Stamp, Error> {
+        Stamp::with_cadence(gpx, options, None)
+    }
+
+    /// Штамп с известным медианным каденсом трека, см. `activity::cadence`.
+    /// Каденс уточняет автоматическое определение типа активности
+    pub fn with_cadence(gpx: &Gpx, options: &Options, cadence: Option<f64>) -> Result<Stamp, Error> {
+        let header = Header::with_cadence(gpx, options, cadence)?;
+        let mut profile = *header.activity.profile();
+        if let Some(smoothing) = options.smoothing {
+            profile.speed_filter.smoothing = smoothing;
+        }
+        if let Some(window) = options.elevation_window {
+            profile.elevation_filter.window = window;
+        }
+        if let Some(threshold) = options.elevation_threshold {
+            profile.elevation_filter.threshold = threshold;
+        }
+        let profile = &profile;
+        let ways = segments(gpx);
+        let mut parts: Vec<Segment> = vec!();
 
-impl From<&Gpx> for Stamp {
-    fn from(gpx: &Gpx) -> Stamp {
-        let track: &Track = &gpx.tracks[0];
-        let way: &Vec<Waypoint> = &track.segments[0].points;
+        for track in &gpx.tracks {
+            for segment in track.segments.iter().filter(|s| !s.points.is_empty()) {
+                let way: &[&[Waypoint]] = &[segment.points.as_slice()];
 
-        Stamp {
-            header: Header::from(gpx),
-            timing: Timing::try_from(way).ok(),
-            velocity: Velocity::try_from(way).ok(),
-            elevation: Elevation::try_from(way).ok(),
+                parts.push(Segment {
+                    track: track.name.clone(),
+                    length: ways_distance(way) as usize,
+                    timing: Timing::new(way, profile).ok(),
+                    velocity: Velocity::new(way, profile).ok(),
+                    elevation: Elevation::new(way, profile).ok(),
+                });
+            }
         }
+
+        Ok(Stamp {
+            header,
+            timing: Timing::new(&ways, profile).ok(),
+            velocity: Velocity::new(&ways, profile).ok(),
+            elevation: Elevation::new(&ways, profile).ok(),
+            segments: parts,
+            splits: Split::from_ways(&ways, options.split, profile),
+            efforts: options.efforts.iter()
+                .filter_map(|effort| BestEffort::new(&ways, *effort))
+                .collect(),
+            climbs: Climb::from_ways(&ways, &options.climb_rule, profile),
+            gradient: Gradient::new(&ways, options.grade_section, profile).ok(),
+            pauses: Pause::from_ways(&ways, profile),
+        })
+    }
+}
+
+impl TryFrom<&Gpx> for Stamp {
+    type Error = Error;
+
+    fn try_from(gpx: &Gpx) -> Result<Self, Self::Error> {
+        Stamp::new(gpx, &Options::default())
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+/// Общие сведения о треке
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Header {
     pub track: Option<String>, // Название трека
+    #[serde(with = "json::option_datetime")]
     pub date: Option<OffsetDateTime>, // Дата начала активности
     pub activity: Activity, // Тип активности
     pub length: usize, // Протяженность трека в метрах
@@ -51,88 +163,384 @@ pub struct Header {
     pub gps_density: usize, // Кол-во GPS-показаний на км пути
 }
 
-impl From<&Gpx> for Header {
-    fn from(gpx: &Gpx) -> Header {
-        let track: &Track = &gpx.tracks[0];
-        let way: &Vec<Waypoint> = &track.segments[0].points;
-        let date = way[0].time;
+impl Header {
+    pub fn new(gpx: &Gpx, options: &Options) -> Result<Header, Error> {
+        Header::with_cadence(gpx, options, None)
+    }
+
+    /// Тип активности берется из параметров, затем из элемента <type>
+    /// трека, и лишь затем определяется по самим данным трека и каденсу
+    pub fn with_cadence(gpx: &Gpx, options: &Options, cadence: Option<f64>) -> Result<Header, Error> {
+        let ways = segments(gpx);
+        if ways.is_empty() {
+            return Err(Error::NoTracks);
+        }
+
+        let points: usize = ways.iter().map(|way| way.len()).sum();
+        let distance = ways_distance(&ways);
+        if distance <= 0.0 {
+            return Err(Error::ShortRoute);
+        }
+        let date = ways[0][0].time;
+        let activity = options.activity
+            .or_else(|| from_gpx_type(gpx))
+            .or_else(|| detect(&ways, cadence))
+            .unwrap_or(Activity::Cycling);
 
-        Header {
-            track: track.name.clone(),
-            date: if date.is_some() { Some(OffsetDateTime::from(date.unwrap())) } else { None },
-            activity: Activity::Cycling,
-            length: way_distance(way) as usize,
+        Ok(Header {
+            track: gpx.tracks.iter().find_map(|track| track.name.clone()),
+            date: date.map(OffsetDateTime::from),
+            activity,
+            length: distance as usize,
             device: gpx.creator.clone(),
-            gps_density: way.len() / (way_distance(way) / 1000.0) as usize,
+            gps_density: (points as f64 / (distance / 1000.0)) as usize,
+        })
+    }
+}
+
+impl TryFrom<&Gpx> for Header {
+    type Error = Error;
+
+    fn try_from(gpx: &Gpx) -> Result<Self, Self::Error> {
+        Header::new(gpx, &Options::default())
+    }
+}
+
+/// Статистика по отдельному сегменту трека
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Segment {
+    pub track: Option<String>, // Название трека, которому принадлежит сегмент
+    pub length: usize, // Протяженность сегмента в метрах
+    pub timing: Option<Timing>,
+    pub velocity: Option<Velocity>,
+    pub elevation: Option<Elevation>,
+}
+
+/// Отрезок пути фиксированной длины. Последний отрезок может быть короче
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Split {
+    pub length: usize, // Протяженность отрезка в метрах
+    pub timing: Option<Timing>, // Общее и чистое время прохождения отрезка
+    #[serde(with = "json::option_speed")]
+    pub speed: Option<usize>, // Средняя скорость по чистому времени, метров/час
+    pub gain: Option<usize>, // Набор высоты в метрах
+    pub loss: Option<usize>, // Сброс высоты в метрах
+}
+
+impl Split {
+    pub fn from_ways(ways: &[&[Waypoint]], step: f64, profile: &Profile) -> Vec<Split> {
+        ways_splits(ways, step, profile).into_iter()
+            .map(|(length, durations, elevations)| {
+                let timing = durations.map(|(total, pure)| Timing { total, pure });
+                let speed = timing
+                    .filter(|time| time.pure.is_positive())
+                    .map(|time| (length / (time.pure.as_seconds_f64() / 3600.0)) as usize);
+
+                Split {
+                    length: length.round() as usize,
+                    timing,
+                    speed,
+                    gain: elevations.map(|(gain, _)| gain.round() as usize),
+                    loss: elevations.map(|(_, loss)| loss.round() as usize),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Цель поиска лучшего результата: самое быстрое прохождение дистанции
+/// или наибольшая дистанция за отрезок времени
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Effort {
+    Distance(usize), // Дистанция в метрах
+    #[serde(with = "json::duration")]
+    Duration(Duration),
+}
+
+
+/// Разбор цели вида "5km", "1mi", "400m", "20min", "1h" или "90s"
+impl FromStr for Effort {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim();
+        let duration = if let Some(number) = value.strip_suffix("min") {
+            number.trim().parse::<f64>().ok().map(|n| n * 60.0)
+        } else if let Some(number) = value.strip_suffix('h') {
+            number.trim().parse::<f64>().ok().map(|n| n * 3600.0)
+        } else if let Some(number) = value.strip_suffix('s') {
+            number.trim().parse::<f64>().ok()
+        } else {
+            None
+        };
+
+        match (duration, parse_distance(value)) {
+            (Some(secs), _) if secs > 0.0 => Ok(Effort::Duration(Duration::seconds_f64(secs))),
+            (None, Some(meters)) if meters > 0.0 => Ok(Effort::Distance(meters.round() as usize)),
+            _ => Err(Error::InvalidEffort(s.to_string())),
+        }
+    }
+}
+
+/// Остановка в пути
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Pause {
+    pub offset: usize, // Расстояние от старта в метрах
+    #[serde(with = "json::datetime")]
+    pub start: OffsetDateTime, // Время начала остановки
+    #[serde(with = "json::duration")]
+    pub duration: Duration,
+}
+
+impl Pause {
+    pub fn from_ways(ways: &[&[Waypoint]], profile: &Profile) -> Vec<Pause> {
+        ways_pauses(ways, profile).into_iter()
+            .map(|(offset, start, duration)| Pause { offset: offset.round() as usize, start, duration })
+            .collect()
+    }
+}
+
+/// Лучший результат на заданной дистанции или отрезке времени
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct BestEffort {
+    pub effort: Effort,
+    pub start: usize, // Индекс первой точки участка среди точек всех сегментов
+    pub end: usize, // Индекс последней точки участка
+    pub distance: usize, // Протяженность участка в метрах
+    #[serde(with = "json::duration")]
+    pub duration: Duration, // Время прохождения участка
+    #[serde(with = "json::option_datetime")]
+    pub time: Option<OffsetDateTime>, // Время начала участка
+}
+
+impl BestEffort {
+    pub fn new(ways: &[&[Waypoint]], effort: Effort) -> Option<BestEffort> {
+        let (start, end, distance, duration) = match effort {
+            Effort::Distance(meters) => ways_best_distance(ways, meters as f64)?,
+            Effort::Duration(limit) => ways_best_duration(ways, limit)?,
+        };
+        let time = ways.iter()
+            .flat_map(|way| way.iter())
+            .nth(start)
+            .and_then(|p| p.time)
+            .map(OffsetDateTime::from);
+
+        Some(BestEffort {
+            effort,
+            start,
+            end,
+            distance: distance.round() as usize,
+            duration,
+            time,
+        })
+    }
+}
+
+/// Категория подъема по шкале от 4-й, самой легкой, до внекатегорийной
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClimbCategory {
+    Cat4,
+    Cat3,
+    Cat2,
+    Cat1,
+    HC,
+}
+
+impl ClimbCategory {
+    /// Категория по произведению длины подъема в метрах на средний уклон
+    /// в процентах. Подъемы с оценкой ниже 8000 категории не имеют
+    pub fn from_score(score: f64) -> Option<ClimbCategory> {
+        match score {
+            score if score >= 80_000.0 => Some(ClimbCategory::HC),
+            score if score >= 64_000.0 => Some(ClimbCategory::Cat1),
+            score if score >= 32_000.0 => Some(ClimbCategory::Cat2),
+            score if score >= 16_000.0 => Some(ClimbCategory::Cat3),
+            score if score >= 8_000.0 => Some(ClimbCategory::Cat4),
+            _ => None,
+        }
+    }
+}
+
+
+/// Отдельный подъем
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Climb {
+    pub start: usize, // Индекс точки начала подъема среди точек всех сегментов
+    pub summit: usize, // Индекс точки вершины
+    pub offset: usize, // Расстояние от старта до начала подъема в метрах
+    pub length: usize, // Длина подъема в метрах
+    pub gain: usize, // Набор высоты в метрах
+    pub average_grade: f64, // Средний уклон в процентах
+    pub max_grade: f64, // Максимальный уклон в процентах
+    pub category: Option<ClimbCategory>,
+}
+
+impl Climb {
+    pub fn from_ways(ways: &[&[Waypoint]], rule: &ClimbRule, profile: &Profile) -> Vec<Climb> {
+        ways_climbs(ways, rule, &profile.elevation_filter).into_iter()
+            .map(|(start, summit, offset, length, gain, average_grade, max_grade)| Climb {
+                start,
+                summit,
+                offset: offset.round() as usize,
+                length: length.round() as usize,
+                gain: gain.round() as usize,
+                average_grade,
+                max_grade,
+                category: ClimbCategory::from_score(length * average_grade),
+            })
+            .collect()
+    }
+}
+
+/// Показатели уклона пути
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Gradient {
+    pub max: f64, // Максимальный уклон подъема в процентах
+    pub min: f64, // Максимальный уклон спуска в процентах, отрицательный
+    pub uphill: f64, // Средний уклон на подъемах
+    pub downhill: f64, // Средний уклон на спусках, отрицательный
+    pub bands: [usize; GRADE_BANDS.len() + 1], // Расстояние в метрах по диапазонам уклона GRADE_BANDS
+}
+
+impl Gradient {
+    pub fn new(ways: &[&[Waypoint]], section: f64, profile: &Profile) -> Result<Gradient, Error> {
+        let grades = ways_grades(ways, section, &profile.elevation_filter).ok_or(Error::NoElevation)?;
+        if grades.is_empty() {
+            return Err(Error::ShortRoute);
         }
+
+        let mut bands = [0.0f64; GRADE_BANDS.len() + 1];
+        let (mut up_length, mut up_climb) = (0.0, 0.0);
+        let (mut down_length, mut down_drop) = (0.0, 0.0);
+        for (length, grade) in &grades {
+            bands[grade_band(*grade)] += length;
+
+            if *grade > 0.0 {
+                up_length += length;
+                up_climb += length * grade;
+            } else if *grade < 0.0 {
+                down_length += length;
+                down_drop += length * grade;
+            }
+        }
+
+        Ok(Gradient {
+            max: grades.iter().map(|(_, grade)| *grade).fold(0.0, f64::max),
+            min: grades.iter().map(|(_, grade)| *grade).fold(0.0, f64::min),
+            uphill: if up_length > 0.0 { up_climb / up_length } else { 0.0 },
+            downhill: if down_length > 0.0 { down_drop / down_length } else { 0.0 },
+            bands: bands.map(|length| length.round() as usize),
+        })
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// Продолжительность активности
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Timing {
+    #[serde(with = "json::duration")]
     pub total: Duration,
+    #[serde(with = "json::duration")]
     pub pure: Duration, // Чистое время, исключая паузы
 }
 
-impl TryFrom<&Vec<Waypoint>> for Timing {
-    type Error = &'static str;
-
-    fn try_from(way: &Vec<Waypoint>) -> Result<Self, Self::Error> {
-        match way_durations(way) {
+impl Timing {
+    pub fn new(ways: &[&[Waypoint]], profile: &Profile) -> Result<Timing, Error> {
+        match ways_durations(ways, profile) {
             Some((total, pure)) => {
                 Ok(Timing {
                     total,
                     pure,
                 })
             },
-            _ => Err("Not correct timing data!"),
+            _ => Err(Error::NoTime),
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// Показатели скорости. В JSON выводятся в метрах в секунду
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Velocity {
+    #[serde(with = "json::speed")]
     pub average: usize, // Средняя скорость, метров/час
+    #[serde(with = "json::speed")]
     pub maximum: usize, // Максимальная скорость, метров/час
+    #[serde(with = "json::speed")]
+    pub raw_average: usize, // Средняя скорость без фильтрации GPS-шума, метров/час
+    #[serde(with = "json::speed")]
+    pub raw_maximum: usize, // Максимальная скорость без фильтрации GPS-шума, метров/час
 }
 
-impl TryFrom<&Vec<Waypoint>> for Velocity {
-    type Error = &'static str;
-
-    fn try_from(way: &Vec<Waypoint>) -> Result<Self, Self::Error> {
-        let average = avg_speed(way);
-        let maximum = max_speed(way);
+impl Velocity {
+    pub fn new(ways: &[&[Waypoint]], profile: &Profile) -> Result<Velocity, Error> {
+        let average = ways_avg_speed(ways, profile);
+        let maximum = ways_max_speed(ways, profile);
+        let raw_average = ways_raw_avg_speed(ways, profile);
+        let raw_maximum = ways_raw_max_speed(ways);
 
-        match (average, maximum) {
-            (Some(avg), Some(max)) => {
+        match (average, maximum, raw_average, raw_maximum) {
+            (Some(avg), Some(max), Some(raw_avg), Some(raw_max)) => {
                 Ok(Velocity {
                     average: avg as usize,
                     maximum: max as usize,
+                    raw_average: raw_avg as usize,
+                    raw_maximum: raw_max as usize,
                 })
             },
-            _ => Err("Not correct velocity data!"),
+            _ => Err(Error::NoTime),
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// Показатели подъема и высоты
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Elevation {
     pub total: usize, // Общий подъем в метрах
     pub maximum: usize, // Максимальный непрерывный подъем в метрах
+    pub loss: usize, // Общий спуск в метрах
+    pub lowest: i64, // Минимальная высота в метрах
+    pub highest: i64, // Максимальная высота в метрах
+    pub start: i64, // Высота в начале пути
+    pub end: i64, // Высота в конце пути
 }
 
-impl TryFrom<&Vec<Waypoint>> for Elevation {
-    type Error = &'static str;
+impl Elevation {
+    pub fn new(ways: &[&[Waypoint]], profile: &Profile) -> Result<Elevation, Error> {
+        let filter = &profile.elevation_filter;
 
-    fn try_from(way: &Vec<Waypoint>) -> Result<Self, Self::Error> {
-        match way_elevations(way) {
-            Some((total, maximum)) => {
+        match (ways_elevations(ways, filter), ways_altitudes(ways, filter)) {
+            (Some((total, loss, maximum)), Some((lowest, highest, start, end))) => {
                 Ok(Elevation {
-                    total: total as usize,
-                    maximum: maximum as usize,
+                    total: total.round() as usize,
+                    maximum: maximum.round() as usize,
+                    loss: loss.round() as usize,
+                    lowest: lowest.round() as i64,
+                    highest: highest.round() as i64,
+                    start: start.round() as i64,
+                    end: end.round() as i64,
                 })
             },
-            _ => Err("Not correct elevation data!"),
+            _ => Err(Error::NoElevation),
         }
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn climb_categories_by_score() {
+        assert_eq!(ClimbCategory::from_score(7_999.0), None);
+        assert_eq!(ClimbCategory::from_score(8_000.0), Some(ClimbCategory::Cat4));
+        assert_eq!(ClimbCategory::from_score(15_999.0), Some(ClimbCategory::Cat4));
+        assert_eq!(ClimbCategory::from_score(16_000.0), Some(ClimbCategory::Cat3));
+        assert_eq!(ClimbCategory::from_score(32_000.0), Some(ClimbCategory::Cat2));
+        assert_eq!(ClimbCategory::from_score(64_000.0), Some(ClimbCategory::Cat1));
+        assert_eq!(ClimbCategory::from_score(79_999.0), Some(ClimbCategory::Cat1));
+        assert_eq!(ClimbCategory::from_score(80_000.0), Some(ClimbCategory::HC));
+        assert!(ClimbCategory::HC > ClimbCategory::Cat1 && ClimbCategory::Cat3 > ClimbCategory::Cat4);
+    }
+}
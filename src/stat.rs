@@ -1,15 +1,15 @@
+use std::str::FromStr;
+
 use geoutils::Location;
 use gpx::Waypoint;
-use phf::phf_map;
 use time::{OffsetDateTime, Duration};
 
-
-static PAUSE_GAPS: phf::Map<&'static str, (Duration, f64)> = phf_map! {
-    "cycling" => (Duration::minutes(2), 5000.0 * (2.0 / 60.0)),
-};
+use crate::activity::Profile;
+use crate::error::Error;
 
 
-pub fn way_distance(way: &Vec<Waypoint>) -> f64 {
+/// Протяженность пути в метрах
+pub fn way_distance(way: &[Waypoint]) -> f64 {
     let mut distance: f64 = 0.0;
 
     if way.len() > 1 {
@@ -24,45 +24,124 @@ pub fn way_distance(way: &Vec<Waypoint>) -> f64 {
     distance
 }
 
-// Возвращает статистику относительно суммарного подъема, а
-// также максимального непрерывного подъема в метрах
-pub fn way_elevations(way: &Vec<Waypoint>) -> Option<(f64, f64)> {
+/// Суммарная протяженность нескольких сегментов. Расстояние между концом
+/// одного сегмента и началом следующего не учитывается
+pub fn ways_distance(ways: &[&[Waypoint]]) -> f64 {
+    ways.iter().map(|way| way_distance(way)).sum()
+}
+
+/// Настройки фильтрации шума высоты
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ElevationFilter {
+    pub window: usize, // Окно скользящего среднего по точкам. Единица отключает сглаживание
+    pub threshold: f64, // Гистерезис: изменение высоты меньше порога в метрах не учитывается
+}
+
+impl Default for ElevationFilter {
+    fn default() -> ElevationFilter {
+        ElevationFilter { window: 5, threshold: 3.0 }
+    }
+}
+
+/// Высоты точек пути, сглаженные скользящим средним по window точкам.
+/// Если хотя бы у одной точки нет высоты, то None
+pub fn smooth_elevations(way: &[Waypoint], window: usize) -> Option<Vec<f64>> {
+    let elevations: Vec<f64> = way.iter().map(|p| p.elevation).collect::<Option<Vec<f64>>>()?;
+    let half = window.max(1) / 2;
+
+    Some((0..elevations.len())
+        .map(|i| {
+            let from = i.saturating_sub(half);
+            let to = (i + half + 1).min(elevations.len());
+
+            elevations[from..to].iter().sum::<f64>() / (to - from) as f64
+        })
+        .collect())
+}
+
+/// Возвращает статистику относительно суммарного подъема, суммарного
+/// спуска, а также максимального непрерывного подъема в метрах.
+/// Высоты предварительно сглаживаются, а изменения высоты меньше порога
+/// гистерезиса копятся до тех пор, пока не превысят его
+pub fn way_elevations(way: &[Waypoint], filter: &ElevationFilter) -> Option<(f64, f64, f64)> {
+    let elevations = smooth_elevations(way, filter.window)?;
     let mut max_elev: f64 = 0.0;
     let mut cur_elev: f64 = 0.0;
     let mut total_elev: f64 = 0.0;
+    let mut total_loss: f64 = 0.0;
 
-    if way.len() > 1 {
-        for (p1, p2) in way.iter().zip(way[1..].iter()) {
-            if p2.elevation? >= p1.elevation? {
-                let diff = p2.elevation? - p1.elevation?;
+    let mut level = match elevations.first() {
+        Some(elev) => *elev,
+        None => return Some((0.0, 0.0, 0.0)),
+    };
+    for elev in elevations {
+        let diff = elev - level;
 
-                cur_elev += diff;
-                total_elev += diff;
-            } else {
-                max_elev =  if cur_elev > max_elev { cur_elev } else { max_elev };
-                cur_elev = 0.0;
-            }
+        if diff >= filter.threshold {
+            cur_elev += diff;
+            total_elev += diff;
+            level = elev;
+        } else if -diff >= filter.threshold {
+            max_elev = if cur_elev > max_elev { cur_elev } else { max_elev };
+            cur_elev = 0.0;
+            total_loss -= diff;
+            level = elev;
         }
     }
+    // Подъем может продолжаться до самого конца пути
+    max_elev = if cur_elev > max_elev { cur_elev } else { max_elev };
 
-    Some((total_elev, max_elev))
+    Some((total_elev, total_loss, max_elev))
 }
 
-// Максимальный показатель скорости между двумя
-// последовательными gps-показателями
-pub fn max_speed(way: &Vec<Waypoint>) -> Option<f64> {
-    let mut max_speed: f64 = 0.0;
+/// Подъем и спуск по нескольким сегментам. Непрерывный подъем не может
+/// продолжаться через разрыв между сегментами
+pub fn ways_elevations(ways: &[&[Waypoint]], filter: &ElevationFilter) -> Option<(f64, f64, f64)> {
+    let mut max_elev: f64 = 0.0;
+    let mut total_elev: f64 = 0.0;
+    let mut total_loss: f64 = 0.0;
+
+    for way in ways {
+        let (total, loss, max) = way_elevations(way, filter)?;
+
+        total_elev += total;
+        total_loss += loss;
+        max_elev = if max > max_elev { max } else { max_elev };
+    }
+
+    Some((total_elev, total_loss, max_elev))
+}
+
+/// Минимальная, максимальная, начальная и конечная высота пути
+/// по сглаженным высотам точек
+pub fn ways_altitudes(ways: &[&[Waypoint]], filter: &ElevationFilter) -> Option<(f64, f64, f64, f64)> {
+    let mut elevations: Vec<f64> = vec!();
+    for way in ways {
+        elevations.extend(smooth_elevations(way, filter.window)?);
+    }
 
-    for (p1, p2) in way.iter().zip(way[1..].iter()) {
-        let from = Location::new(p1.point().y(), p1.point().x());
-        let to = Location::new(p2.point().y(), p2.point().x());
+    let first = *elevations.first()?;
+    let last = *elevations.last()?;
+    let min = elevations.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = elevations.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    Some((min, max, first, last))
+}
 
-        // TODO возможно стоит ввести ограничение на минимальное
-        // расстояние между точками для поиска макс. скорости
-        let distance = from.distance_to(&to).unwrap().meters();
+/// Максимальный показатель скорости между двумя
+/// последовательными gps-показателями без какой-либо фильтрации.
+/// Пары точек с одинаковым временем пропускаются
+pub fn raw_max_speed(way: &[Waypoint]) -> Option<f64> {
+    let mut max_speed: f64 = 0.0;
+
+    for (p1, p2) in way.iter().zip(way.iter().skip(1)) {
+        let distance = points_distance(p1, p2);
         let t1: OffsetDateTime = p1.time?.into();
         let t2: OffsetDateTime = p2.time?.into();
         let duration = (t2 - t1).abs().whole_seconds();
+        if duration == 0 {
+            continue;
+        }
 
         let speed = distance / (duration as f64 / Duration::HOUR.whole_seconds() as f64);
 
@@ -74,77 +153,249 @@ pub fn max_speed(way: &Vec<Waypoint>) -> Option<f64> {
     Some(max_speed)
 }
 
-// Средняя скорость прохождения всего пути исключая паузы.
-// Показатель измеряется в метры/секунды
-pub fn avg_speed(way: &Vec<Waypoint>) -> Option<f64> {
-    let start_point = &way[0];
-    let finish_point = &way[way.len() - 1];
-    let start_time = start_point.time;
-    let finish_time = finish_point.time;
+/// Максимальная нефильтрованная скорость по нескольким сегментам, метров/час
+pub fn ways_raw_max_speed(ways: &[&[Waypoint]]) -> Option<f64> {
+    let mut max_speed_val: f64 = 0.0;
+
+    for way in ways {
+        let speed = raw_max_speed(way)?;
+
+        max_speed_val = if speed > max_speed_val { speed } else { max_speed_val };
+    }
+
+    Some(max_speed_val)
+}
+
+// Расстояние в метрах и продолжительность движения по сегменту,
+// исключая найденные паузы
+fn moving_stats(way: &[Waypoint], profile: &Profile) -> Option<(f64, Duration)> {
+    let st: OffsetDateTime = way.first()?.time?.into();
+    let ft: OffsetDateTime = way.last()?.time?.into();
 
     let mut distance: f64 = way_distance(way);
+    let mut clean_duration = ft - st;
 
-    match (start_time, finish_time) {
-        (Some(x), Some(y)) => {
-            let st: OffsetDateTime = x.into();
-            let ft: OffsetDateTime = y.into();
-            let mut clean_duration = Some(ft - st);
+    for (dur, _, _) in find_pauses(way, profile.pause_duration, profile.pause_distance) {
+        clean_duration -= dur;
+        distance -= profile.pause_distance;
+    }
 
-            let (dur_gap, dist_gap) = PAUSE_GAPS.get("cycling").unwrap().clone();
-            for (dur, _, _) in find_pauses(way, dur_gap, dist_gap) {
-                clean_duration = Some(clean_duration.unwrap() - dur);
-                distance -= dist_gap;
-            }
+    Some((distance, clean_duration))
+}
 
-            let clean_dur_hours = Some(clean_duration.unwrap().whole_seconds() as f64 /
-                                   Duration::HOUR.whole_seconds() as f64);
+/// Средняя скорость прохождения пути исключая паузы, посчитанная
+/// по полной дистанции без фильтрации. Показатель измеряется в метры/час
+pub fn raw_avg_speed(way: &[Waypoint], profile: &Profile) -> Option<f64> {
+    ways_raw_avg_speed(&[way], profile)
+}
 
-            Some(distance / clean_dur_hours.unwrap())
-        },
-        _ => None,
+/// Нефильтрованная средняя скорость по нескольким сегментам. Разрывы
+/// между сегментами считаются паузами и в расчет не входят
+pub fn ways_raw_avg_speed(ways: &[&[Waypoint]], profile: &Profile) -> Option<f64> {
+    let mut distance: f64 = 0.0;
+    let mut clean_duration = Duration::ZERO;
+
+    for way in ways.iter().filter(|way| !way.is_empty()) {
+        let (dist, dur) = moving_stats(way, profile)?;
+
+        distance += dist;
+        clean_duration += dur;
+    }
+
+    let clean_dur_hours = clean_duration.whole_seconds() as f64 /
+                          Duration::HOUR.whole_seconds() as f64;
+
+    Some(distance / clean_dur_hours)
+}
+
+/// Способ сглаживания ряда скоростей
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Smoothing {
+    None,
+    Median(usize), // Скользящая медиана по окну из заданного числа значений
+    Kalman { process: f64, measurement: f64 }, // Дисперсии шума процесса и измерений, (км/ч)²
+}
+
+/// Настройки фильтрации GPS-шума при подсчете скорости
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpeedFilter {
+    pub min_distance: f64, // Минимальная длина окна в метрах
+    pub min_time: f64, // Минимальная продолжительность окна в секундах
+    pub smoothing: Smoothing,
+}
+
+/// Разбор способа сглаживания вида "none", "median", "median:7" или "kalman"
+impl FromStr for Smoothing {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim().to_lowercase();
+        let (name, param) = match value.split_once(':') {
+            Some((name, param)) => (name, Some(param)),
+            None => (value.as_str(), None),
+        };
+
+        match (name, param.map(|p| p.parse::<usize>())) {
+            ("none", None) => Ok(Smoothing::None),
+            ("median", None) => Ok(Smoothing::Median(5)),
+            ("median", Some(Ok(size))) if size > 0 => Ok(Smoothing::Median(size)),
+            ("kalman", None) => Ok(Smoothing::Kalman { process: 4.0, measurement: 9.0 }),
+            _ => Err(Error::InvalidSmoothing(s.to_string())),
+        }
     }
 }
 
-// Так как паузы часто никак не обозначены внутри GPX-файла, то
-// нужно попытаться найти их самостоятельно. Сделать мы это можем,
-// лишь опираясь на какие-то пороговые значения.
-// dur_gap - ограничение по продолжительности между двумя соседними gps-показаниями
-// dist_gap - ограничение на минимальную длину дистанции
-// Фактически dist_gap / dur_gap - это ограничение по скорости для учета паузы,
-// между двумя соседними gps-показаниями
+fn median_smooth(speeds: &[f64], size: usize) -> Vec<f64> {
+    let half = size / 2;
+
+    (0..speeds.len())
+        .map(|i| {
+            let from = i.saturating_sub(half);
+            let to = (i + half + 1).min(speeds.len());
+            let mut window = speeds[from..to].to_vec();
+            window.sort_by(|a, b| a.total_cmp(b));
+
+            window[window.len() / 2]
+        })
+        .collect()
+}
+
+// Одномерный фильтр Калмана с моделью постоянной скорости
+fn kalman_smooth(speeds: &[f64], process: f64, measurement: f64) -> Vec<f64> {
+    let mut result: Vec<f64> = vec!();
+    let mut estimate = match speeds.first() {
+        Some(speed) => speed / 1000.0,
+        None => return result,
+    };
+    let mut error = measurement;
+
+    for speed in speeds {
+        error += process;
+        let gain = error / (error + measurement);
+        estimate += gain * (speed / 1000.0 - estimate);
+        error *= 1.0 - gain;
+
+        result.push(estimate * 1000.0);
+    }
+
+    result
+}
+
+/// Отфильтрованный ряд скоростей движения. Каждое значение - это пара
+/// из продолжительности окна в секундах и скорости в метрах/час.
+/// Соседние gps-показания объединяются в окна не короче min_distance и
+/// min_time, окна-паузы и окна со скоростью выше правдоподобной для
+/// активности отбрасываются, а оставшийся ряд сглаживается
+pub fn speed_series(way: &[Waypoint], profile: &Profile) -> Option<Vec<(f64, f64)>> {
+    let filter = &profile.speed_filter;
+    let mut windows: Vec<(f64, f64)> = vec!();
+    let mut start = 0;
+    let mut distance = 0.0;
+
+    for end in 1..way.len() {
+        distance += points_distance(&way[end - 1], &way[end]);
+
+        let t1: OffsetDateTime = way[start].time?.into();
+        let t2: OffsetDateTime = way[end].time?.into();
+        let secs = (t2 - t1).abs().as_seconds_f64();
+        if distance < filter.min_distance || secs < filter.min_time || secs <= 0.0 {
+            continue;
+        }
+
+        let pause = secs >= profile.pause_duration.as_seconds_f64()
+            && distance <= profile.pause_distance;
+        let speed = distance / (secs / 3600.0);
+        if !pause && speed <= profile.max_speed {
+            windows.push((secs, speed));
+        }
+
+        start = end;
+        distance = 0.0;
+    }
+
+    let speeds: Vec<f64> = windows.iter().map(|(_, speed)| *speed).collect();
+    let smoothed = match filter.smoothing {
+        Smoothing::None => speeds,
+        Smoothing::Median(size) => median_smooth(&speeds, size.max(1)),
+        Smoothing::Kalman { process, measurement } => kalman_smooth(&speeds, process, measurement),
+    };
+
+    Some(windows.iter().zip(smoothed).map(|((secs, _), speed)| (*secs, speed)).collect())
+}
+
+fn ways_speed_series(ways: &[&[Waypoint]], profile: &Profile) -> Option<Vec<(f64, f64)>> {
+    let mut series: Vec<(f64, f64)> = vec!();
+
+    for way in ways {
+        series.extend(speed_series(way, profile)?);
+    }
+
+    Some(series)
+}
+
+/// Максимальная скорость по отфильтрованному ряду скоростей, метров/час
+pub fn max_speed(way: &[Waypoint], profile: &Profile) -> Option<f64> {
+    ways_max_speed(&[way], profile)
+}
+
+/// Максимальная скорость по нескольким сегментам, метров/час
+pub fn ways_max_speed(ways: &[&[Waypoint]], profile: &Profile) -> Option<f64> {
+    let series = ways_speed_series(ways, profile)?;
+
+    Some(series.iter().map(|(_, speed)| *speed).fold(0.0, f64::max))
+}
+
+/// Средняя скорость движения по отфильтрованному ряду скоростей,
+/// взвешенная по продолжительности окон. Показатель измеряется в метры/час
+pub fn avg_speed(way: &[Waypoint], profile: &Profile) -> Option<f64> {
+    ways_avg_speed(&[way], profile)
+}
+
+/// Средняя скорость по нескольким сегментам. Разрывы между
+/// сегментами считаются паузами и в расчет не входят
+pub fn ways_avg_speed(ways: &[&[Waypoint]], profile: &Profile) -> Option<f64> {
+    let series = ways_speed_series(ways, profile)?;
+    let secs: f64 = series.iter().map(|(secs, _)| secs).sum();
+    if secs <= 0.0 {
+        return None;
+    }
+
+    Some(series.iter().map(|(secs, speed)| secs * speed).sum::<f64>() / secs)
+}
+
+/// Так как паузы часто никак не обозначены внутри GPX-файла, то
+/// нужно попытаться найти их самостоятельно. Сделать мы это можем,
+/// лишь опираясь на какие-то пороговые значения.
+/// dur_gap - ограничение по продолжительности между двумя соседними gps-показаниями
+/// dist_gap - ограничение на минимальную длину дистанции
+/// Фактически dist_gap / dur_gap - это ограничение по скорости для учета паузы,
+/// между двумя соседними gps-показаниями
 pub fn find_pauses(
-    way: &Vec<Waypoint>,
+    way: &[Waypoint],
     dur_gap: Duration,
     dist_gap: f64
 ) -> Vec<(Duration, &Waypoint, &Waypoint)> {
     let mut pauses: Vec<(Duration, &Waypoint, &Waypoint)> = vec!();
-    for (p1, p2) in way.iter().zip(way[1..].iter()) {
-        match (p1.time, p2.time) {
-            (Some(t1), Some(t2)) => {
-                let time_point1: OffsetDateTime = t1.into();
-                let time_point2: OffsetDateTime = t2.into();
-                let pause_duration = (time_point2 - time_point1).abs();
-
-                if  pause_duration >= dur_gap {
-                    let pair = vec!(p1.clone(), p2.clone());
-                    let dist = way_distance(&pair);
-
-                    if dist <= dist_gap {
-                        pauses.push((pause_duration, &p1, &p2));
-                    };
-                };
+    for (p1, p2) in way.iter().zip(way.iter().skip(1)) {
+        if let (Some(t1), Some(t2)) = (p1.time, p2.time) {
+            let time_point1: OffsetDateTime = t1.into();
+            let time_point2: OffsetDateTime = t2.into();
+            let pause_duration = (time_point2 - time_point1).abs();
 
-            },
-            _ => {},
+            if pause_duration >= dur_gap && points_distance(p1, p2) <= dist_gap {
+                pauses.push((pause_duration, p1, p2));
+            }
         }
     }
 
     pauses
 }
 
-pub fn way_durations(way: &Vec<Waypoint>) -> Option<(Duration, Duration)> {
-    let start_point = &way[0];
-    let finish_point = &way[way.len() - 1];
+/// Общая и чистая(исключая паузы) продолжительность пути.
+/// У пустого пути продолжительности нет
+pub fn way_durations(way: &[Waypoint], profile: &Profile) -> Option<(Duration, Duration)> {
+    let start_point = way.first()?;
+    let finish_point = way.last()?;
 
     let start_time = start_point.time;
     let finish_time = finish_point.time;
@@ -154,17 +405,703 @@ pub fn way_durations(way: &Vec<Waypoint>) -> Option<(Duration, Duration)> {
             let st: OffsetDateTime = x.into();
             let ft: OffsetDateTime = y.into();
 
-            let total_duration = Some(ft - st);
-            let mut clean_duration = total_duration.clone();
+            let total_duration = ft - st;
+            let mut clean_duration = total_duration;
 
-            let (dur_gap, dist_gap) = PAUSE_GAPS.get("cycling").unwrap().clone();
-            for (dur, _, _) in find_pauses(way, dur_gap, dist_gap) {
-                clean_duration = Some(clean_duration.unwrap() - dur);
+            for (dur, _, _) in find_pauses(way, profile.pause_duration, profile.pause_distance) {
+                clean_duration -= dur;
             }
 
-            Some((total_duration.unwrap(), clean_duration.unwrap()))
+            Some((total_duration, clean_duration))
 
         },
         _ => None,
     }
 }
+
+/// Общее время считается от первой точки первого сегмента до
+/// последней точки последнего, а чистое время складывается из
+/// чистого времени каждого сегмента. Таким образом, разрывы между
+/// сегментами учитываются как паузы. Пустые сегменты пропускаются
+pub fn ways_durations(ways: &[&[Waypoint]], profile: &Profile) -> Option<(Duration, Duration)> {
+    let ways: Vec<&[Waypoint]> = ways.iter().copied().filter(|way| !way.is_empty()).collect();
+
+    let st: OffsetDateTime = ways.first()?.first()?.time?.into();
+    let ft: OffsetDateTime = ways.last()?.last()?.time?.into();
+
+    let mut clean_duration = Duration::ZERO;
+    for way in &ways {
+        let (_, pure) = way_durations(way, profile)?;
+
+        clean_duration += pure;
+    }
+
+    Some((ft - st, clean_duration))
+}
+
+/// Паузы всего пути: расстояние от старта до места паузы в метрах,
+/// время ее начала и продолжительность. Разрывы между сегментами
+/// тоже считаются паузами
+pub fn ways_pauses(ways: &[&[Waypoint]], profile: &Profile) -> Vec<(f64, OffsetDateTime, Duration)> {
+    let mut pauses: Vec<(f64, OffsetDateTime, Duration)> = vec!();
+    let mut passed = 0.0;
+    let mut previous: Option<&Waypoint> = None;
+
+    for way in ways.iter().filter(|way| !way.is_empty()) {
+        if let (Some(t1), Some(t2)) = (previous.and_then(|p| p.time), way[0].time) {
+            let (t1, t2): (OffsetDateTime, OffsetDateTime) = (t1.into(), t2.into());
+
+            pauses.push((passed, t1, (t2 - t1).abs()));
+        }
+
+        let found = find_pauses(way, profile.pause_duration, profile.pause_distance);
+        let mut found = found.iter().peekable();
+        for (num, p) in way.iter().enumerate() {
+            while let Some((duration, _, _)) = found.next_if(|(_, p1, _)| std::ptr::eq(*p1, p)) {
+                if let Some(time) = p.time {
+                    pauses.push((passed, time.into(), *duration));
+                }
+            }
+            if num + 1 < way.len() {
+                passed += points_distance(p, &way[num + 1]);
+            }
+        }
+        previous = way.last();
+    }
+
+    pauses
+}
+
+/// Расстояние между двумя точками в метрах
+pub fn points_distance(p1: &Waypoint, p2: &Waypoint) -> f64 {
+    let from = Location::new(p1.point().y(), p1.point().x());
+    let to = Location::new(p2.point().y(), p2.point().x());
+
+    from.distance_to(&to).unwrap().meters()
+}
+
+// Накопитель показателей текущего отрезка при разбивке пути
+#[derive(Default)]
+struct SplitAcc {
+    length: f64,
+    elapsed: f64,
+    moving: f64,
+    gain: f64,
+    loss: f64,
+}
+
+impl SplitAcc {
+    // Добавляет к отрезку долю part от перехода между двумя gps-показаниями
+    fn add(&mut self, part: f64, dist: f64, secs: f64, pause: bool, elev: f64) {
+        self.length += dist * part;
+        self.elapsed += secs * part;
+        if !pause {
+            self.moving += secs * part;
+        }
+        if elev > 0.0 {
+            self.gain += elev * part;
+        } else {
+            self.loss -= elev * part;
+        }
+    }
+}
+
+type SplitStat = (f64, Option<(Duration, Duration)>, Option<(f64, f64)>);
+
+/// Разбивка пути на отрезки по step метров. Точка пересечения границы
+/// отрезка находится линейной интерполяцией между соседними gps-показаниями.
+/// Для каждого отрезка возвращается его длина, общее и чистое время, а
+/// также набор и сброс сглаженной высоты. Время и высота отсутствуют, если
+/// их нет хотя бы у одной точки пути. Разрывы между сегментами в отрезки не входят
+pub fn ways_splits(ways: &[&[Waypoint]], step: f64, profile: &Profile) -> Vec<SplitStat> {
+    let mut splits: Vec<SplitStat> = vec!();
+    if step <= 0.0 {
+        return splits;
+    }
+
+    let has_time = ways.iter().all(|way| way.iter().all(|p| p.time.is_some()));
+    let has_elev = ways.iter().all(|way| way.iter().all(|p| p.elevation.is_some()));
+    let finish = |acc: &SplitAcc| -> SplitStat {
+        (
+            acc.length,
+            if has_time {
+                Some((Duration::seconds_f64(acc.elapsed), Duration::seconds_f64(acc.moving)))
+            } else {
+                None
+            },
+            if has_elev { Some((acc.gain, acc.loss)) } else { None },
+        )
+    };
+
+    let mut acc = SplitAcc::default();
+    for way in ways {
+        let elevations = smooth_elevations(way, profile.elevation_filter.window);
+
+        for (num, (p1, p2)) in way.iter().zip(way.iter().skip(1)).enumerate() {
+            let dist = points_distance(p1, p2);
+            let secs = match (p1.time, p2.time) {
+                (Some(t1), Some(t2)) => {
+                    let t1: OffsetDateTime = t1.into();
+                    let t2: OffsetDateTime = t2.into();
+
+                    (t2 - t1).abs().as_seconds_f64()
+                },
+                _ => 0.0,
+            };
+            let elev = match &elevations {
+                Some(elevations) => elevations[num + 1] - elevations[num],
+                None => 0.0,
+            };
+            let pause = secs >= profile.pause_duration.as_seconds_f64()
+                && dist <= profile.pause_distance;
+
+            if dist <= 0.0 {
+                acc.add(1.0, dist, secs, pause, elev);
+                continue;
+            }
+
+            let mut done = 0.0;
+            while acc.length + dist * (1.0 - done) >= step {
+                let part = (step - acc.length) / dist;
+
+                acc.add(part, dist, secs, pause, elev);
+                splits.push(finish(&acc));
+                acc = SplitAcc::default();
+                done += part;
+            }
+            acc.add(1.0 - done, dist, secs, pause, elev);
+        }
+    }
+
+    // Последний неполный отрезок, если он не совсем короткий
+    if acc.length >= 1.0 {
+        splits.push(finish(&acc));
+    }
+
+    splits
+}
+
+// Накопленное расстояние в метрах и время в секундах от начала сегмента
+// для каждой точки. Если хотя бы у одной точки нет времени, то None
+fn way_profile(way: &[Waypoint]) -> Option<(Vec<f64>, Vec<f64>)> {
+    let start: OffsetDateTime = way.first()?.time?.into();
+    let mut distances: Vec<f64> = vec!(0.0);
+    let mut seconds: Vec<f64> = vec!();
+
+    for p in way {
+        let t: OffsetDateTime = p.time?.into();
+
+        seconds.push((t - start).as_seconds_f64());
+    }
+    for (p1, p2) in way.iter().zip(way.iter().skip(1)) {
+        distances.push(distances[distances.len() - 1] + points_distance(p1, p2));
+    }
+
+    Some((distances, seconds))
+}
+
+type EffortStat = (usize, usize, f64, Duration);
+
+// Скользящее окно по каждому сегменту. Окно сужается, пока участок
+// удовлетворяет условию fits, и лучшим считается участок с наибольшей
+// средней скоростью. Индексы считаются в общей последовательности точек
+// всех сегментов
+fn best_window<F>(ways: &[&[Waypoint]], fits: F) -> Option<EffortStat>
+where
+    F: Fn(&[f64], &[f64], usize, usize) -> bool,
+{
+    let mut best: Option<EffortStat> = None;
+    let mut offset = 0;
+
+    for way in ways {
+        if let Some((distances, seconds)) = way_profile(way) {
+            let mut start = 0;
+
+            for end in 0..way.len() {
+                while start + 1 < end && fits(&distances, &seconds, start + 1, end) {
+                    start += 1;
+                }
+                if start >= end || !fits(&distances, &seconds, start, end) {
+                    continue;
+                }
+
+                // Участок без прошедшего времени, например скачок GPS,
+                // дал бы бесконечную скорость
+                let distance = distances[end] - distances[start];
+                let secs = seconds[end] - seconds[start];
+                if secs <= 0.0 {
+                    continue;
+                }
+                let is_better = match best {
+                    Some((_, _, best_dist, best_dur)) => {
+                        distance / secs > best_dist / best_dur.as_seconds_f64()
+                    },
+                    None => true,
+                };
+                if is_better {
+                    best = Some((offset + start, offset + end, distance, Duration::seconds_f64(secs)));
+                }
+            }
+        }
+
+        offset += way.len();
+    }
+
+    best
+}
+
+/// Самый быстрый непрерывный участок пути протяженностью не менее distance метров.
+/// Возвращает индексы первой и последней точки участка в общей последовательности
+/// точек всех сегментов, протяженность участка и время его прохождения
+pub fn ways_best_distance(ways: &[&[Waypoint]], distance: f64) -> Option<EffortStat> {
+    best_window(ways, |distances, _, start, end| distances[end] - distances[start] >= distance)
+}
+
+/// Участок пути продолжительностью не менее duration, на котором
+/// была пройдена наибольшая дистанция. Результат аналогичен ways_best_distance
+pub fn ways_best_duration(ways: &[&[Waypoint]], duration: Duration) -> Option<EffortStat> {
+    let limit = duration.as_seconds_f64();
+
+    best_window(ways, |_, seconds, start, end| seconds[end] - seconds[start] >= limit)
+}
+
+/// Правило выделения подъемов
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClimbRule {
+    pub min_length: f64, // Минимальная длина подъема в метрах
+    pub min_gain: f64, // Минимальный набор высоты в метрах
+    pub min_grade: f64, // Минимальный средний уклон в процентах
+    pub max_dip: f64, // Допустимый спуск внутри подъема в метрах
+    pub grade_window: f64, // Длина участка в метрах для поиска максимального уклона
+}
+
+impl Default for ClimbRule {
+    fn default() -> ClimbRule {
+        ClimbRule {
+            min_length: 500.0,
+            min_gain: 20.0,
+            min_grade: 3.0,
+            max_dip: 10.0,
+            grade_window: 100.0,
+        }
+    }
+}
+
+type ClimbStat = (usize, usize, f64, f64, f64, f64, f64);
+
+// Максимальный уклон в процентах на участках не короче window метров
+fn max_grade(distances: &[f64], elevations: &[f64], window: f64) -> f64 {
+    let mut grade: f64 = 0.0;
+    let mut start = 0;
+
+    for end in 0..distances.len() {
+        while start + 1 < end && distances[end] - distances[start + 1] >= window {
+            start += 1;
+        }
+
+        let length = distances[end] - distances[start];
+        if length >= window {
+            grade = grade.max((elevations[end] - elevations[start]) / length * 100.0);
+        }
+    }
+
+    grade
+}
+
+/// Поиск подъемов по сглаженной высоте. Подъем начинается в самой низкой
+/// точке и заканчивается на вершине, после которой высота опускается более
+/// чем на max_dip. Подходящими считаются подъемы, удовлетворяющие правилу
+/// по длине, набору высоты и среднему уклону. Для каждого подъема возвращаются
+/// индексы начала и вершины в общей последовательности точек всех сегментов,
+/// расстояние от старта до начала подъема, его длина, набор высоты, средний
+/// и максимальный уклон в процентах
+pub fn ways_climbs(ways: &[&[Waypoint]], rule: &ClimbRule, filter: &ElevationFilter) -> Vec<ClimbStat> {
+    let mut climbs: Vec<ClimbStat> = vec!();
+    let mut offset = 0;
+    let mut passed = 0.0;
+
+    // В пустом сегменте нет ни вершины, ни точек для смещения индексов
+    for way in ways.iter().filter(|way| !way.is_empty()) {
+        let elevations = match smooth_elevations(way, filter.window) {
+            Some(elevations) => elevations,
+            None => return vec!(),
+        };
+        let mut distances: Vec<f64> = vec!(0.0);
+        for (p1, p2) in way.iter().zip(way.iter().skip(1)) {
+            distances.push(distances[distances.len() - 1] + points_distance(p1, p2));
+        }
+
+        let mut start = 0;
+        let mut summit = 0;
+        for i in 0..=way.len() {
+            // За последней точкой сегмента подъем обязательно заканчивается
+            let ended = i == way.len() || elevations[summit] - elevations[i] > rule.max_dip;
+
+            if ended {
+                let length = distances[summit] - distances[start];
+                let gain = elevations[summit] - elevations[start];
+
+                if length >= rule.min_length && gain >= rule.min_gain
+                    && gain / length * 100.0 >= rule.min_grade {
+                    climbs.push((
+                        offset + start,
+                        offset + summit,
+                        passed + distances[start],
+                        length,
+                        gain,
+                        gain / length * 100.0,
+                        max_grade(&distances[start..=summit], &elevations[start..=summit], rule.grade_window),
+                    ));
+                }
+                if i == way.len() {
+                    break;
+                }
+
+                start = i;
+                summit = i;
+            } else if elevations[i] >= elevations[summit] {
+                summit = i;
+            } else if elevations[i] < elevations[start] {
+                start = i;
+                summit = i;
+            }
+        }
+
+        offset += way.len();
+        passed += distances[distances.len() - 1];
+    }
+
+    climbs
+}
+
+/// Границы диапазонов уклона в процентах. Диапазонов на один больше,
+/// чем границ: от крутых спусков до крутых подъемов
+pub const GRADE_BANDS: [f64; 6] = [-10.0, -5.0, -2.0, 2.0, 5.0, 10.0];
+
+/// Номер диапазона уклона из GRADE_BANDS
+pub fn grade_band(grade: f64) -> usize {
+    GRADE_BANDS.iter().filter(|bound| grade >= **bound).count()
+}
+
+/// Разбивка сегмента на участки не короче section метров, последний
+/// участок может быть короче. Для каждого участка возвращаются индексы
+/// его первой и последней точки в сегменте, длина и уклон в процентах,
+/// посчитанный по сглаженной высоте
+pub fn way_grade_sections(way: &[Waypoint], section: f64, filter: &ElevationFilter) -> Option<Vec<(usize, usize, f64, f64)>> {
+    let elevations = smooth_elevations(way, filter.window)?;
+    let mut sections: Vec<(usize, usize, f64, f64)> = vec!();
+    let mut start = 0;
+    let mut length = 0.0;
+
+    for end in 1..way.len() {
+        length += points_distance(&way[end - 1], &way[end]);
+
+        if length >= section || (end == way.len() - 1 && length > 0.0) {
+            sections.push((start, end, length, (elevations[end] - elevations[start]) / length * 100.0));
+            start = end;
+            length = 0.0;
+        }
+    }
+
+    Some(sections)
+}
+
+/// Разбивка пути на участки не короче section метров с уклоном
+/// в процентах, посчитанным по сглаженной высоте. Каждый участок -
+/// пара из его длины и уклона, см. `way_grade_sections`
+pub fn ways_grades(ways: &[&[Waypoint]], section: f64, filter: &ElevationFilter) -> Option<Vec<(f64, f64)>> {
+    let mut grades: Vec<(f64, f64)> = vec!();
+
+    for way in ways {
+        grades.extend(way_grade_sections(way, section, filter)?.into_iter().map(|(_, _, length, grade)| (length, grade)));
+    }
+
+    Some(grades)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activity::Activity;
+
+    // Градусов долготы на метр вдоль экватора, где расстояние
+    // пропорционально разнице долгот
+    const DEGREES: f64 = 1.0 / 111_319.490_793_273_57;
+
+    // Точка на экваторе в meters метрах от нулевого меридиана
+    fn point(meters: f64, elevation: Option<f64>, secs: Option<i64>) -> Waypoint {
+        let mut p = Waypoint::new(geo_types::Point::new(meters * DEGREES, 0.0));
+        p.elevation = elevation;
+        p.time = secs.map(|secs| OffsetDateTime::from_unix_timestamp(1_700_000_000 + secs).unwrap().into());
+
+        p
+    }
+
+    // Путь из точек (расстояние, высота, секунды от начала)
+    fn way(points: &[(f64, f64, i64)]) -> Vec<Waypoint> {
+        points.iter().map(|(meters, elev, secs)| point(*meters, Some(*elev), Some(*secs))).collect()
+    }
+
+    // Профиль без сглаживания высоты
+    fn profile() -> Profile {
+        Profile {
+            elevation_filter: ElevationFilter { window: 1, threshold: 3.0 },
+            ..*Activity::Cycling.profile()
+        }
+    }
+
+    fn assert_near(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-3, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn splits_interpolate_boundaries() {
+        let track = way(&[(0.0, 0.0, 0), (600.0, 6.0, 60), (1500.0, 15.0, 150)]);
+        let splits = ways_splits(&[&track], 1000.0, &profile());
+
+        assert_eq!(splits.len(), 2);
+        let (length, time, elev) = splits[0];
+        let (total, moving) = time.unwrap();
+        let (gain, loss) = elev.unwrap();
+        assert_near(length, 1000.0);
+        assert_near(total.as_seconds_f64(), 100.0);
+        assert_near(moving.as_seconds_f64(), 100.0);
+        assert_near(gain, 10.0);
+        assert_near(loss, 0.0);
+
+        let (length, time, elev) = splits[1];
+        assert_near(length, 500.0);
+        assert_near(time.unwrap().0.as_seconds_f64(), 50.0);
+        assert_near(elev.unwrap().0, 5.0);
+    }
+
+    #[test]
+    fn splits_skip_gaps_between_segments() {
+        let first = way(&[(0.0, 0.0, 0), (500.0, 0.0, 50)]);
+        let second = way(&[(10_000.0, 0.0, 1000), (10_700.0, 0.0, 1070)]);
+        let splits = ways_splits(&[&first, &second], 1000.0, &profile());
+
+        assert_eq!(splits.len(), 2);
+        assert_near(splits[0].0, 1000.0);
+        assert_near(splits[0].1.unwrap().0.as_seconds_f64(), 100.0);
+        assert_near(splits[1].0, 200.0);
+    }
+
+    #[test]
+    fn splits_without_time_or_elevation() {
+        let track = vec!(point(0.0, None, None), point(1500.0, None, None));
+        let splits = ways_splits(&[&track], 1000.0, &profile());
+
+        assert_eq!(splits.len(), 2);
+        assert!(splits.iter().all(|(_, time, elev)| time.is_none() && elev.is_none()));
+        assert!(ways_splits(&[&track], 0.0, &profile()).is_empty());
+    }
+
+    #[test]
+    fn best_efforts_find_fastest_window() {
+        let track = way(&[(0.0, 0.0, 0), (100.0, 0.0, 20), (200.0, 0.0, 30), (300.0, 0.0, 40), (400.0, 0.0, 60)]);
+
+        let (start, end, distance, duration) = ways_best_distance(&[&track], 200.0).unwrap();
+        assert_eq!((start, end), (1, 3));
+        assert_near(distance, 200.0);
+        assert_eq!(duration, Duration::seconds(20));
+
+        let (start, end, distance, duration) = ways_best_duration(&[&track], Duration::seconds(20)).unwrap();
+        assert_eq!((start, end), (1, 3));
+        assert_near(distance, 200.0);
+        assert_eq!(duration, Duration::seconds(20));
+
+        assert!(ways_best_distance(&[&track], 1000.0).is_none());
+    }
+
+    #[test]
+    fn best_efforts_count_indices_across_segments() {
+        let slow = way(&[(0.0, 0.0, 0), (100.0, 0.0, 100), (200.0, 0.0, 200)]);
+        let fast = way(&[(1000.0, 0.0, 300), (1100.0, 0.0, 310), (1200.0, 0.0, 320)]);
+
+        let (start, end, _, duration) = ways_best_distance(&[&slow, &fast], 200.0).unwrap();
+        assert_eq!((start, end), (3, 5));
+        assert_eq!(duration, Duration::seconds(20));
+    }
+
+    #[test]
+    fn best_efforts_skip_windows_without_elapsed_time() {
+        let jump = way(&[(0.0, 0.0, 0), (500.0, 0.0, 0)]);
+
+        assert!(ways_best_distance(&[&jump], 100.0).is_none());
+    }
+
+    // Профиль без сглаживания скорости с окнами не меньше 10 метров и 5 секунд
+    fn speed_profile(smoothing: Smoothing) -> Profile {
+        Profile {
+            speed_filter: SpeedFilter { min_distance: 10.0, min_time: 5.0, smoothing },
+            ..profile()
+        }
+    }
+
+    #[test]
+    fn speed_series_joins_points_into_min_windows() {
+        let points: Vec<(f64, f64, i64)> = (0..=12).map(|num| (num as f64 * 5.0, 0.0, num * 2)).collect();
+        let series = speed_series(&way(&points), &speed_profile(Smoothing::None)).unwrap();
+
+        assert_eq!(series.len(), 4);
+        for (secs, speed) in series {
+            assert_near(secs, 6.0);
+            assert_near(speed, 15.0 / 6.0 * 3600.0);
+        }
+    }
+
+    #[test]
+    fn speed_series_drops_pauses_and_implausible_speeds() {
+        let track = way(&[(0.0, 0.0, 0), (20.0, 0.0, 300), (40.0, 0.0, 305), (10_040.0, 0.0, 315), (10_060.0, 0.0, 320)]);
+        let series = speed_series(&track, &speed_profile(Smoothing::None)).unwrap();
+
+        assert_eq!(series.len(), 2);
+        assert_near(series[0].1, 20.0 / 5.0 * 3600.0);
+        assert_near(series[1].1, 20.0 / 5.0 * 3600.0);
+
+        let untimed = vec!(point(0.0, None, None), point(100.0, None, None));
+        assert!(speed_series(&untimed, &speed_profile(Smoothing::None)).is_none());
+    }
+
+    #[test]
+    fn speed_smoothing_removes_spikes() {
+        assert_eq!(median_smooth(&[10.0, 10.0, 100.0, 10.0, 10.0], 3), vec!(10.0; 5));
+        assert_eq!(median_smooth(&[1.0, 5.0, 3.0], 1), vec!(1.0, 5.0, 3.0));
+
+        let constant = kalman_smooth(&[20_000.0; 5], 4.0, 9.0);
+        assert!(constant.iter().all(|speed| (speed - 20_000.0).abs() < 1e-6));
+
+        let step = kalman_smooth(&[10_000.0, 30_000.0, 30_000.0], 4.0, 9.0);
+        assert!(step[1] > 10_000.0 && step[1] < 30_000.0);
+        assert!(step[2] > step[1] && step[2] < 30_000.0);
+        assert!(kalman_smooth(&[], 4.0, 9.0).is_empty());
+    }
+
+    // Путь через каждые 100 метров с заданными высотами
+    fn elevation_way(elevations: &[f64]) -> Vec<Waypoint> {
+        elevations.iter().enumerate().map(|(num, elev)| point(num as f64 * 100.0, Some(*elev), None)).collect()
+    }
+
+    #[test]
+    fn elevations_apply_hysteresis() {
+        let filter = ElevationFilter { window: 1, threshold: 3.0 };
+
+        let (gain, loss, max) = way_elevations(&elevation_way(&[0.0, 2.0, 4.0, 3.0, 1.0, 5.0]), &filter).unwrap();
+        assert_near(gain, 8.0);
+        assert_near(loss, 3.0);
+        assert_near(max, 4.0);
+
+        let noise = elevation_way(&[0.0, 1.0, 0.0, 2.0, 0.0, 1.0]);
+        assert_eq!(way_elevations(&noise, &filter), Some((0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn elevations_accumulate_slow_climbs() {
+        let filter = ElevationFilter { window: 1, threshold: 3.0 };
+        let (gain, loss, max) = way_elevations(&elevation_way(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0]), &filter).unwrap();
+
+        assert_near(gain, 6.0);
+        assert_near(loss, 0.0);
+        assert_near(max, 6.0);
+    }
+
+    #[test]
+    fn elevations_require_every_point() {
+        let mut track = elevation_way(&[0.0, 10.0, 20.0]);
+        track[1].elevation = None;
+
+        assert!(way_elevations(&track, &ElevationFilter::default()).is_none());
+        assert_eq!(smooth_elevations(&elevation_way(&[0.0, 3.0, 6.0]), 3), Some(vec!(1.5, 3.0, 4.5)));
+    }
+
+    #[test]
+    fn climbs_tolerate_small_dips() {
+        let filter = ElevationFilter { window: 1, threshold: 3.0 };
+        let flat = elevation_way(&[0.0, 0.0, 0.0]);
+        let hill = elevation_way(&[0.0, 10.0, 20.0, 30.0, 40.0, 50.0, 60.0, 70.0, 80.0, 90.0, 100.0, 95.0, 105.0, 120.0, 60.0, 50.0]);
+
+        let climbs = ways_climbs(&[&flat, &hill], &ClimbRule::default(), &filter);
+        assert_eq!(climbs.len(), 1);
+        let (start, summit, offset, length, gain, average, max) = climbs[0];
+        assert_eq!((start, summit), (3, 16));
+        assert_near(offset, 200.0);
+        assert_near(length, 1300.0);
+        assert_near(gain, 120.0);
+        assert_near(average, 120.0 / 1300.0 * 100.0);
+        assert_near(max, 15.0);
+    }
+
+    #[test]
+    fn climbs_follow_the_rule() {
+        let filter = ElevationFilter { window: 1, threshold: 3.0 };
+        let bump = elevation_way(&[0.0, 5.0, 10.0, 15.0, 0.0]);
+        let gentle = elevation_way(&[0.0, 2.0, 4.0, 6.0, 8.0, 10.0, 12.0, 14.0, 16.0, 18.0, 20.0, 22.0]);
+
+        assert!(ways_climbs(&[&bump], &ClimbRule::default(), &filter).is_empty());
+        assert!(ways_climbs(&[&gentle], &ClimbRule::default(), &filter).is_empty());
+        assert_eq!(ways_climbs(&[&gentle], &ClimbRule { min_grade: 2.0, ..ClimbRule::default() }, &filter).len(), 1);
+    }
+
+    #[test]
+    fn grades_split_into_sections() {
+        let filter = ElevationFilter { window: 1, threshold: 3.0 };
+        let up = elevation_way(&[0.0, 5.0, 10.0, 15.0, 20.0, 25.0, 30.0, 35.0, 40.0, 45.0, 50.0]);
+        let down = elevation_way(&[50.0, 40.0, 30.0]);
+
+        let sections = way_grade_sections(&up, 300.0, &filter).unwrap();
+        let bounds: Vec<(usize, usize)> = sections.iter().map(|(start, end, _, _)| (*start, *end)).collect();
+        assert_eq!(bounds, vec!((0, 3), (3, 6), (6, 9), (9, 10)));
+
+        let grades = ways_grades(&[&up, &down], 300.0, &filter).unwrap();
+        assert_eq!(grades.len(), 5);
+        for (length, grade) in &grades[..3] {
+            assert_near(*length, 300.0);
+            assert_near(*grade, 5.0);
+        }
+        assert_near(grades[3].0, 100.0);
+        assert_near(grades[4].0, 200.0);
+        assert_near(grades[4].1, -10.0);
+    }
+
+    #[test]
+    fn grades_fall_into_bands() {
+        let bands: Vec<usize> = [-15.0, -10.0, -3.0, 0.0, 2.0, 5.0, 12.0].iter().map(|grade| grade_band(*grade)).collect();
+
+        assert_eq!(bands, vec!(0, 1, 2, 3, 4, 5, 6));
+        assert_eq!(grade_band(9.99), 5);
+    }
+
+    #[test]
+    fn empty_segments_are_skipped() {
+        let profile = profile();
+        let filter = profile.elevation_filter;
+        let empty: Vec<Waypoint> = vec!();
+        let track = way(&[(0.0, 0.0, 0), (600.0, 30.0, 60), (1200.0, 60.0, 120), (1800.0, 0.0, 180)]);
+        let with_empty: [&[Waypoint]; 3] = [&empty, &track, &empty];
+        let only: [&[Waypoint]; 1] = [&track];
+
+        assert_eq!(raw_max_speed(&empty), Some(0.0));
+        assert!(find_pauses(&empty, profile.pause_duration, profile.pause_distance).is_empty());
+        assert_eq!(way_durations(&empty, &profile), None);
+        assert_eq!(ways_durations(&[&empty], &profile), None);
+
+        assert_eq!(ways_distance(&with_empty), ways_distance(&only));
+        assert_eq!(ways_raw_max_speed(&with_empty), ways_raw_max_speed(&only));
+        assert_eq!(ways_raw_avg_speed(&with_empty, &profile), ways_raw_avg_speed(&only, &profile));
+        assert_eq!(ways_max_speed(&with_empty, &profile), ways_max_speed(&only, &profile));
+        assert_eq!(ways_avg_speed(&with_empty, &profile), ways_avg_speed(&only, &profile));
+        assert_eq!(ways_durations(&with_empty, &profile), ways_durations(&only, &profile));
+        assert_eq!(ways_pauses(&with_empty, &profile), ways_pauses(&only, &profile));
+        assert_eq!(ways_elevations(&with_empty, &filter), ways_elevations(&only, &filter));
+        assert_eq!(ways_altitudes(&with_empty, &filter), ways_altitudes(&only, &filter));
+        assert_eq!(ways_splits(&with_empty, 1000.0, &profile), ways_splits(&only, 1000.0, &profile));
+        assert_eq!(ways_grades(&with_empty, 300.0, &filter), ways_grades(&only, 300.0, &filter));
+        assert_eq!(ways_best_distance(&with_empty, 500.0).map(|best| best.2), ways_best_distance(&only, 500.0).map(|best| best.2));
+
+        let climbs = ways_climbs(&with_empty, &ClimbRule::default(), &filter);
+        assert_eq!(climbs.len(), 1);
+        assert_eq!((climbs[0].0, climbs[0].1), (0, 2));
+    }
+}
@@ -0,0 +1,419 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+use svg::Document;
+use svg::node::Text as NodeText;
+use svg::node::element::{Group, Line, Rectangle, Text};
+use time::{Date, Duration, Month};
+
+use crate::error::Error;
+use crate::json;
+use crate::locale::{fill, Lang};
+use crate::render::{format_distance, format_duration, format_elevation, format_speed, svg_document, tick_step};
+use crate::stamp::Stamp;
+use crate::theme::Theme;
+use crate::units::Units;
+
+
+/// Календарный период итогов
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Week, // Неделя с понедельника, по ISO 8601
+    #[default]
+    Month,
+    Year,
+}
+
+impl Period {
+    /// Первый день периода, в который попадает дата
+    pub fn start(&self, date: Date) -> Date {
+        match self {
+            Period::Week => date - Duration::days(date.weekday().number_days_from_monday() as i64),
+            Period::Month => date.replace_day(1).unwrap(),
+            Period::Year => date.replace_ordinal(1).unwrap(),
+        }
+    }
+
+    /// Первый день следующего периода
+    pub fn next(&self, start: Date) -> Date {
+        match self {
+            Period::Week => start + Duration::weeks(1),
+            Period::Month if start.month() == Month::December => {
+                Date::from_calendar_date(start.year() + 1, Month::January, 1).unwrap()
+            },
+            Period::Month => Date::from_calendar_date(start.year(), start.month().next(), 1).unwrap(),
+            Period::Year => Date::from_calendar_date(start.year() + 1, Month::January, 1).unwrap(),
+        }
+    }
+
+    /// Обозначение периода: "2023-W23", "2023-06" или "2023"
+    pub fn label(&self, start: Date) -> String {
+        match self {
+            Period::Week => {
+                let (year, week, _) = start.to_iso_week_date();
+
+                format!("{}-W{:02}", year, week)
+            },
+            Period::Month => format!("{}-{:02}", start.year(), start.month() as u8),
+            Period::Year => format!("{}", start.year()),
+        }
+    }
+
+    // Индекс в массивах названий периодов каталога сообщений
+    pub(crate) fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Period::Week => write!(f, "week"),
+            Period::Month => write!(f, "month"),
+            Period::Year => write!(f, "year"),
+        }
+    }
+}
+
+impl FromStr for Period {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "week" | "weekly" => Ok(Period::Week),
+            "month" | "monthly" => Ok(Period::Month),
+            "year" | "yearly" => Ok(Period::Year),
+            _ => Err(Error::UnknownPeriod(s.to_string())),
+        }
+    }
+}
+
+/// Итоги одного периода
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Total {
+    pub period: String, // Обозначение периода, см. `Period::label`
+    #[serde(with = "json::date")]
+    pub start: Date, // Первый день периода
+    pub count: usize, // Кол-во активностей
+    pub distance: usize, // Суммарная протяженность в метрах
+    #[serde(with = "json::duration")]
+    pub moving: Duration, // Суммарное чистое время
+    pub gain: usize, // Суммарный подъем в метрах
+    pub longest: usize, // Протяженность самой длинной активности в метрах
+    #[serde(with = "json::option_speed")]
+    pub fastest: Option<usize>, // Наибольшая средняя скорость в метрах/час
+}
+
+impl Total {
+    // Итоги по штампам, попавшим в период. Штампы без времени или
+    // высоты не добавляют чистого времени и подъема
+    fn new(period: String, start: Date, stamps: &[&Stamp]) -> Total {
+        Total {
+            period,
+            start,
+            count: stamps.len(),
+            distance: stamps.iter().map(|stamp| stamp.header.length).sum(),
+            moving: stamps.iter().filter_map(|stamp| stamp.timing).map(|time| time.pure).sum(),
+            gain: stamps.iter().filter_map(|stamp| stamp.elevation).map(|elev| elev.total).sum(),
+            longest: stamps.iter().map(|stamp| stamp.header.length).max().unwrap_or(0),
+            fastest: stamps.iter().filter_map(|stamp| stamp.velocity).map(|velo| velo.average).max(),
+        }
+    }
+}
+
+/// Итоги по календарным периодам
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Summary {
+    pub period: Period,
+    pub totals: Vec<Total>, // Периоды с активностями в хронологическом порядке
+    pub undated: usize, // Кол-во штампов без даты, не попавших ни в один период
+}
+
+/// Группировка штампов по календарным периодам по дате начала активности в UTC.
+/// В итоги входят только периоды с активностями, пустые промежутки
+/// между ними заполняются лишь на графике
+pub fn summarize(stamps: &[&Stamp], period: Period) -> Summary {
+    let mut periods: BTreeMap<Date, Vec<&Stamp>> = BTreeMap::new();
+    let mut undated = 0;
+    for stamp in stamps {
+        match stamp.header.date {
+            Some(date) => periods.entry(period.start(date.date())).or_default().push(*stamp),
+            None => undated += 1,
+        }
+    }
+
+    let totals = periods.into_iter()
+        .map(|(start, members)| Total::new(period.label(start), start, &members))
+        .collect();
+
+    Summary { period, totals, undated }
+}
+
+// Все периоды от первого до последнего с итогами, для пустых - без итогов
+fn chart_periods(summary: &Summary) -> Vec<(String, Option<&Total>)> {
+    let period = summary.period;
+    let mut bars: Vec<(String, Option<&Total>)> = vec!();
+    let mut start = match summary.totals.first() {
+        Some(first) => first.start,
+        None => return bars,
+    };
+
+    for total in &summary.totals {
+        while start < total.start {
+            bars.push((period.label(start), None));
+            start = period.next(start);
+        }
+        bars.push((total.period.clone(), Some(total)));
+        start = period.next(total.start);
+    }
+
+    bars
+}
+
+/// Таблица итогов по периодам со строкой общих итогов на заданном языке
+/// и в заданных единицах измерения
+pub fn summary_to_text(summary: &Summary, lang: Lang, units: Units) -> String {
+    let text = lang.messages();
+    let unknown = text.unknown.to_string();
+    let header = [text.period, text.count, text.length, text.moving_time, text.ascent, text.longest, text.fastest];
+
+    let row = |label: &str, count: usize, distance: usize, moving: Duration, gain: usize, longest: usize, fastest: Option<usize>| {
+        [
+            label.to_string(),
+            format!("{}", count),
+            format_distance(distance as f64, text, units),
+            format_duration(moving),
+            format_elevation(gain as f64, text, units),
+            format_distance(longest as f64, text, units),
+            fastest.map_or(unknown.clone(), |speed| format_speed(speed as f64, text, units)),
+        ].join(" | ")
+    };
+
+    let totals = &summary.totals;
+    let mut rows: Vec<String> = vec!(format!("{}: \n{}", text.periods[summary.period.index()], header.join(" | ")));
+    for total in totals {
+        rows.push(row(&total.period, total.count, total.distance, total.moving, total.gain, total.longest, total.fastest));
+    }
+    rows.push(row(
+        text.summary_total,
+        totals.iter().map(|total| total.count).sum(),
+        totals.iter().map(|total| total.distance).sum(),
+        totals.iter().map(|total| total.moving).sum(),
+        totals.iter().map(|total| total.gain).sum(),
+        totals.iter().map(|total| total.longest).max().unwrap_or(0),
+        totals.iter().filter_map(|total| total.fastest).max(),
+    ));
+    if summary.undated > 0 {
+        rows.push(format!("\n{}", fill(text.undated, &[&summary.undated])));
+    }
+
+    rows.join("\n")
+}
+
+/// Столбчатая диаграмма протяженности по периодам. Над столбцами подписаны
+/// значения, под столбцами - обозначения периодов. Пустые периоды между
+/// активностями остаются на оси без столбцов. Если столбцы слишком узкие,
+/// подписи поворачиваются, а значения не выводятся
+pub fn summary_to_svg(summary: &Summary, theme: &Theme, lang: Lang, units: Units) -> Document {
+    let text = lang.messages();
+    let colors = &theme.colors;
+    let padding = theme.padding;
+    let totals = chart_periods(summary);
+
+    let bars = totals.len().max(1) as f64;
+    let width = theme.width.max(bars * 12.0);
+    let step = width / bars;
+    let height = theme.width * 0.6;
+    let rotate = step < 30.0;
+    let labels_height = if rotate { 30.0 } else { 10.0 };
+
+    let highest = summary.totals.iter().map(|total| units.distance(total.distance as f64)).fold(0.0, f64::max);
+    let grid_step = tick_step(highest, &[1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0], 5.0);
+    let top = ((highest / grid_step).ceil() * grid_step).max(grid_step);
+    let y_scale = height / top;
+
+    // Подписи линий сетки выводятся слева от диаграммы
+    let gutter = 20.0;
+    let (x, y) = (padding + gutter, padding + 28.0);
+    let mut content = Group::new()
+        .add(Text::new()
+             .set("x", padding)
+             .set("y", padding + 10.0)
+             .set("font-size", "0.7em")
+             .set("fill", colors.text.as_str())
+             .add(NodeText::new(text.periods[summary.period.index()])))
+        .add(Text::new()
+             .set("x", padding)
+             .set("y", padding + 19.0)
+             .set("font-size", "0.4em")
+             .set("fill", colors.muted.as_str())
+             .add(NodeText::new(fill(text.summary_chart, &[&text.distance_units[units.index()]]))))
+        .add(Rectangle::new()
+             .set("x", x)
+             .set("y", y)
+             .set("width", width)
+             .set("height", height)
+             .set("fill", colors.panel.as_str()));
+
+    // Линии сетки, "круглые" в единицах расстояния
+    let mut axes = Group::new()
+        .set("font-size", "0.35em")
+        .set("fill", colors.muted.as_str());
+    let mut level = grid_step;
+    while level <= top {
+        let level_y = y + height - level * y_scale;
+
+        axes = axes
+            .add(Line::new()
+                 .set("stroke", colors.grid.as_str())
+                 .set("stroke-width", 0.3)
+                 .set("stroke-dasharray", "2,2")
+                 .set("x1", x)
+                 .set("y1", level_y)
+                 .set("x2", x + width)
+                 .set("y2", level_y))
+            .add(Text::new()
+                 .set("x", x - 2.0)
+                 .set("y", level_y + 1.5)
+                 .set("text-anchor", "end")
+                 .add(NodeText::new(format!("{}", level))));
+        level += grid_step;
+    }
+    content = content.add(axes);
+
+    let mut labels = Group::new()
+        .set("font-size", "0.35em")
+        .set("fill", colors.text.as_str());
+    for (num, (period, total)) in totals.iter().enumerate() {
+        let distance = total.map_or(0.0, |total| units.distance(total.distance as f64));
+        let bar_height = distance * y_scale;
+        let center = x + step * (num as f64 + 0.5);
+
+        content = content.add(Rectangle::new()
+                              .set("x", center - step * 0.35)
+                              .set("y", y + height - bar_height)
+                              .set("width", step * 0.7)
+                              .set("height", bar_height)
+                              .set("fill", colors.route.as_str()));
+        if !rotate && total.is_some() {
+            labels = labels.add(Text::new()
+                                .set("x", center)
+                                .set("y", y + height - bar_height - 1.5)
+                                .set("text-anchor", "middle")
+                                .add(NodeText::new(format!("{:.0}", distance))));
+        }
+
+        let label = Text::new().add(NodeText::new(period.as_str()));
+        labels = labels.add(if rotate {
+            label
+                .set("text-anchor", "end")
+                .set("transform", format!("translate({}, {}) rotate(-90)", center + 1.5, y + height + 2.0))
+        } else {
+            label
+                .set("x", center)
+                .set("y", y + height + 7.0)
+                .set("text-anchor", "middle")
+        });
+    }
+    content = content.add(labels);
+
+    svg_document(theme, width + gutter + padding * 2.0, y + height + labels_height + padding, content)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use gpx::{Gpx, Track, TrackSegment, Waypoint};
+
+    use super::*;
+    use crate::stamp::Options;
+
+    fn day(year: i32, month: u8, day: u8) -> Date {
+        Date::from_calendar_date(year, Month::try_from(month).unwrap(), day).unwrap()
+    }
+
+    // Штамп поездки на length метров, начавшейся в полдень дня date
+    fn stamp(date: Option<Date>, length: usize) -> Stamp {
+        let mut segment = TrackSegment::new();
+        for (num, lon) in [0.0, 0.01, 0.02].into_iter().enumerate() {
+            let mut p = Waypoint::new(geo_types::Point::new(lon, 0.0));
+            p.time = date.map(|date| (date.midnight().assume_utc() + Duration::hours(12) + Duration::minutes(num as i64 * 5)).into());
+            segment.points.push(p);
+        }
+        let mut track = Track::new();
+        track.segments.push(segment);
+
+        let mut stamp = Stamp::new(&Gpx { tracks: vec!(track), ..Gpx::default() }, &Options::default()).unwrap();
+        stamp.header.length = length;
+
+        stamp
+    }
+
+    #[test]
+    fn periods_follow_iso_weeks() {
+        // 1 января 2023 года - воскресенье последней недели 2022 года
+        assert_eq!(Period::Week.start(day(2023, 1, 1)), day(2022, 12, 26));
+        assert_eq!(Period::Week.label(Period::Week.start(day(2023, 1, 1))), "2022-W52");
+        assert_eq!(Period::Week.label(Period::Week.start(day(2023, 1, 2))), "2023-W01");
+        assert_eq!(Period::Week.label(Period::Week.start(day(2021, 1, 3))), "2020-W53");
+        assert_eq!(Period::Week.next(day(2022, 12, 26)), day(2023, 1, 2));
+
+        assert_eq!(Period::Month.start(day(2023, 12, 31)), day(2023, 12, 1));
+        assert_eq!(Period::Month.next(day(2023, 12, 1)), day(2024, 1, 1));
+        assert_eq!(Period::Month.label(day(2023, 6, 1)), "2023-06");
+        assert_eq!(Period::Year.start(day(2024, 2, 29)), day(2024, 1, 1));
+        assert_eq!(Period::Year.label(day(2024, 1, 1)), "2024");
+    }
+
+    #[test]
+    fn stamps_are_grouped_by_period() {
+        let stamps = [
+            stamp(Some(day(2023, 3, 10)), 30_000),
+            stamp(Some(day(2023, 1, 20)), 20_000),
+            stamp(None, 5_000),
+            stamp(Some(day(2023, 1, 5)), 10_000),
+        ];
+        let refs: Vec<&Stamp> = stamps.iter().collect();
+        let summary = summarize(&refs, Period::Month);
+
+        let periods: Vec<(&str, usize, usize, usize)> = summary.totals.iter()
+            .map(|total| (total.period.as_str(), total.count, total.distance, total.longest))
+            .collect();
+        assert_eq!(periods, vec!(("2023-01", 2, 30_000, 20_000), ("2023-03", 1, 30_000, 30_000)));
+        assert_eq!(summary.undated, 1);
+    }
+
+    #[test]
+    fn empty_periods_only_on_chart() {
+        let stamps = [stamp(Some(day(2023, 1, 5)), 10_000), stamp(Some(day(2023, 4, 5)), 10_000)];
+        let refs: Vec<&Stamp> = stamps.iter().collect();
+        let summary = summarize(&refs, Period::Month);
+        assert_eq!(summary.totals.len(), 2);
+
+        let bars: Vec<(String, bool)> = chart_periods(&summary).into_iter().map(|(label, total)| (label, total.is_some())).collect();
+        assert_eq!(bars, vec!(
+            ("2023-01".to_string(), true),
+            ("2023-02".to_string(), false),
+            ("2023-03".to_string(), false),
+            ("2023-04".to_string(), true),
+        ));
+    }
+
+    #[test]
+    fn no_dated_stamps() {
+        let stamps = [stamp(None, 10_000)];
+        let refs: Vec<&Stamp> = stamps.iter().collect();
+
+        let summary = summarize(&refs, Period::Week);
+        assert!(summary.totals.is_empty());
+        assert_eq!(summary.undated, 1);
+        assert!(chart_periods(&summary).is_empty());
+
+        let summary = summarize(&[], Period::Year);
+        assert_eq!((summary.totals.len(), summary.undated), (0, 0));
+        assert!(summary_to_text(&summary, Lang::En, Units::Metric).contains("Total | 0 |"));
+    }
+}
@@ -0,0 +1,209 @@
+use crate::locale::{Lang, Messages};
+use crate::render::format_duration;
+use crate::stamp::Stamp;
+use crate::units::Units;
+
+
+// Колонки, значения которых - числа. В Markdown выравниваются вправо
+const NUMERIC_COLUMNS: std::ops::Range<usize> = 4..14;
+
+// Заголовки колонок. Единицы измерения указываются в заголовке,
+// чтобы значения оставались числами
+fn header(text: &Messages, units: Units) -> Vec<String> {
+    let distance = text.distance_units[units.index()];
+    let elevation = text.elevation_units[units.index()];
+    let speed = text.speed_units[units.index()];
+    let pace = format!("{}/{}", text.min, distance);
+
+    vec!(
+        text.file.to_string(),
+        text.track.to_string(),
+        text.date_utc.to_string(),
+        text.activity_short.to_string(),
+        format!("{} ({})", text.length, distance),
+        text.total_time.to_string(),
+        text.moving_time.to_string(),
+        format!("{} ({})", text.avg_speed, speed),
+        format!("{} ({})", text.max_speed, speed),
+        format!("{} ({})", text.avg_pace, pace),
+        format!("{} ({})", text.ascent, elevation),
+        format!("{} ({})", text.descent, elevation),
+        format!("{} ({})", text.min_altitude, elevation),
+        format!("{} ({})", text.max_altitude, elevation),
+        text.device.to_string(),
+    )
+}
+
+// Строка таблицы по штампу одного файла. Неизвестные величины - пустые ячейки
+fn row(file: &str, stamp: &Stamp, lang: Lang, units: Units) -> Vec<String> {
+    let head = &stamp.header;
+    let date = head.date.map_or(String::new(), |date| {
+        format!("{} {:02}:{:02}:{:02}", date.date(), date.hour(), date.minute(), date.second())
+    });
+    let duration = |total: bool| stamp.timing.map_or(String::new(), |time| {
+        format_duration(if total { time.total } else { time.pure })
+    });
+    let speed = |value: usize| format!("{:.2}", units.speed(value as f64));
+    let pace = stamp.velocity
+        .and_then(|velo| units.pace(velo.average as f64))
+        .map_or(String::new(), |secs| {
+            let secs = secs.round() as i64;
+
+            format!("{:02}:{:02}", secs / 60, secs % 60)
+        });
+    let elevation = |value: f64| format!("{:.0}", units.elevation(value));
+
+    vec!(
+        file.to_string(),
+        head.track.clone().unwrap_or_default(),
+        date,
+        lang.activity(head.activity).to_string(),
+        format!("{:.2}", units.distance(head.length as f64)),
+        duration(true),
+        duration(false),
+        stamp.velocity.map_or(String::new(), |velo| speed(velo.average)),
+        stamp.velocity.map_or(String::new(), |velo| speed(velo.maximum)),
+        pace,
+        stamp.elevation.map_or(String::new(), |elev| elevation(elev.total as f64)),
+        stamp.elevation.map_or(String::new(), |elev| elevation(elev.loss as f64)),
+        stamp.elevation.map_or(String::new(), |elev| elevation(elev.lowest as f64)),
+        stamp.elevation.map_or(String::new(), |elev| elevation(elev.highest as f64)),
+        head.device.clone().unwrap_or_default(),
+    )
+}
+
+fn csv_cell(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+/// Таблица CSV по штампам нескольких файлов, по строке на файл.
+/// Заголовки колонок на заданном языке, числа в заданных единицах измерения
+pub fn to_csv(stamps: &[(&str, &Stamp)], lang: Lang, units: Units) -> String {
+    let text = lang.messages();
+    let mut lines: Vec<String> = vec!();
+
+    lines.push(header(text, units).iter().map(|cell| csv_cell(cell)).collect::<Vec<String>>().join(","));
+    for (file, stamp) in stamps {
+        let cells = row(file, stamp, lang, units);
+
+        lines.push(cells.iter().map(|cell| csv_cell(cell)).collect::<Vec<String>>().join(","));
+    }
+
+    lines.join("\n")
+}
+
+/// Таблица Markdown по штампам нескольких файлов, по строке на файл
+pub fn to_markdown(stamps: &[(&str, &Stamp)], lang: Lang, units: Units) -> String {
+    let text = lang.messages();
+    let header = header(text, units);
+    let align: Vec<&str> = (0..header.len())
+        .map(|num| if NUMERIC_COLUMNS.contains(&num) { "---:" } else { "---" })
+        .collect();
+
+    let mut lines: Vec<String> = vec!(
+        format!("| {} |", header.iter().map(|cell| markdown_cell(cell)).collect::<Vec<String>>().join(" | ")),
+        format!("| {} |", align.join(" | ")),
+    );
+    for (file, stamp) in stamps {
+        let cells = row(file, stamp, lang, units);
+
+        lines.push(format!("| {} |", cells.iter().map(|cell| markdown_cell(cell)).collect::<Vec<String>>().join(" | ")));
+    }
+
+    lines.join("\n")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use time::{Duration, OffsetDateTime};
+
+    use super::*;
+    use crate::activity::Activity;
+    use crate::stamp::{Elevation, Header, Timing, Velocity};
+
+    // Поездка со всеми величинами и название трека со служебными символами
+    fn ride() -> Stamp {
+        Stamp {
+            header: Header {
+                track: Some("Ride, \"fast\" | home".to_string()),
+                date: Some(OffsetDateTime::from_unix_timestamp(1_686_380_400).unwrap()),
+                activity: Activity::Cycling,
+                length: 12_340,
+                device: Some("Garmin".to_string()),
+                gps_density: 100,
+            },
+            timing: Some(Timing { total: Duration::hours(1), pure: Duration::minutes(50) }),
+            velocity: Some(Velocity { average: 20_000, maximum: 36_000, raw_average: 21_000, raw_maximum: 40_000 }),
+            elevation: Some(Elevation { total: 300, maximum: 120, loss: 280, lowest: 100, highest: 250, start: 110, end: 130 }),
+            segments: vec!(),
+            splits: vec!(),
+            efforts: vec!(),
+            climbs: vec!(),
+            gradient: None,
+            pauses: vec!(),
+        }
+    }
+
+    // Пробежка без названия, даты, времени и высоты
+    fn run() -> Stamp {
+        Stamp {
+            header: Header {
+                track: None,
+                date: None,
+                activity: Activity::Running,
+                length: 5_000,
+                device: None,
+                gps_density: 100,
+            },
+            timing: None,
+            velocity: None,
+            elevation: None,
+            ..ride()
+        }
+    }
+
+    #[test]
+    fn csv_table() {
+        let (ride, run) = (ride(), run());
+        let csv = to_csv(&[("rides/a.gpx", &ride), ("b,c.gpx", &run)], Lang::En, Units::Metric);
+
+        assert_eq!(csv, [
+            "File,Track,Date (UTC),Activity,Distance (km),Total time,Moving time,Avg speed (km/h),Max speed (km/h),\
+             Avg pace (min/km),Ascent (m),Descent (m),Min altitude (m),Max altitude (m),Created by",
+            "rides/a.gpx,\"Ride, \"\"fast\"\" | home\",2023-06-10 07:00:00,Cycling,12.34,01:00:00,00:50:00,20.00,36.00,03:00,300,280,100,250,Garmin",
+            "\"b,c.gpx\",,,Running,5.00,,,,,,,,,,",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn markdown_table() {
+        let (ride, run) = (ride(), run());
+        let markdown = to_markdown(&[("rides/a.gpx", &ride), ("b,c.gpx", &run)], Lang::En, Units::Metric);
+
+        assert_eq!(markdown, [
+            "| File | Track | Date (UTC) | Activity | Distance (km) | Total time | Moving time | Avg speed (km/h) | Max speed (km/h) \
+             | Avg pace (min/km) | Ascent (m) | Descent (m) | Min altitude (m) | Max altitude (m) | Created by |",
+            "| --- | --- | --- | --- | ---: | ---: | ---: | ---: | ---: | ---: | ---: | ---: | ---: | ---: | --- |",
+            "| rides/a.gpx | Ride, \"fast\" \\| home | 2023-06-10 07:00:00 | Cycling | 12.34 | 01:00:00 | 00:50:00 | 20.00 | 36.00 \
+             | 03:00 | 300 | 280 | 100 | 250 | Garmin |",
+            "| b,c.gpx |  |  | Running | 5.00 |  |  |  |  |  |  |  |  |  |  |",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn cells_are_escaped() {
+        assert_eq!(csv_cell("plain"), "plain");
+        assert_eq!(csv_cell("a\nb"), "\"a\nb\"");
+        assert_eq!(csv_cell("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(markdown_cell("a|b\nc"), "a\\|b c");
+    }
+}
@@ -0,0 +1,144 @@
+use std::fs;
+use std::path::Path;
+
+use phf::phf_map;
+use serde::Deserialize;
+
+use crate::error::Error;
+
+
+/// Ориентация карточки штампа
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    #[default]
+    Portrait, // Панели друг под другом, высота не меньше ширины
+    Landscape, // Первая панель слева, остальные справа от нее
+    Square, // Как горизонтальная, но высотой не меньше ширины
+}
+
+/// Панель карточки штампа
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Panel {
+    Route, // Схема маршрута с лучшими результатами
+    Elevation, // Профиль высот с подъемами
+    Title, // Название трека
+    Stats, // Сводная статистика
+    Splits, // Таблица отрезков
+    Pauses, // Таблица остановок
+}
+
+/// Цвета элементов штампа в любой записи, понятной SVG
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colors {
+    pub background: String, // Подложка
+    pub panel: String, // Фон схемы маршрута и профиля высот
+    pub route: String, // Линия маршрута и контур профиля
+    pub text: String, // Основной текст
+    pub muted: String, // Подписи осей и величин
+    pub grid: String, // Линии сетки и разделители
+    pub climbs: String, // Подъемы на профиле высот
+    pub efforts: Vec<String>, // Участки лучших результатов, по очереди
+    pub grades: [String; 7], // Диапазоны уклона, от крутых спусков к крутым подъемам
+}
+
+/// Шрифт текста штампа
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Font {
+    pub family: String,
+    pub size: f64, // Базовый размер, от него отсчитываются размеры в em
+}
+
+/// Оформление и компоновка штампа. Поля, не указанные в TOML-файле,
+/// берутся из стандартного оформления
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub orientation: Orientation,
+    pub width: f64, // Ширина колонки панелей
+    pub padding: f64, // Отступы между панелями и от края
+    pub map_height: f64, // Наибольшая высота схемы маршрута
+    pub panels: Vec<Panel>, // Панели в порядке отрисовки
+    pub colors: Colors,
+    pub font: Font,
+}
+
+// Встроенные темы, выбираемые по названию
+static PRESETS: phf::Map<&'static str, &'static str> = phf_map! {
+    "default" => include_str!("../themes/default.toml"),
+    "dark" => include_str!("../themes/dark.toml"),
+    "minimal" => include_str!("../themes/minimal.toml"),
+    "landscape" => include_str!("../themes/landscape.toml"),
+    "square" => include_str!("../themes/square.toml"),
+};
+
+impl Default for Colors {
+    fn default() -> Self {
+        Colors {
+            background: "white".to_string(),
+            panel: "lavender".to_string(),
+            route: "purple".to_string(),
+            text: "black".to_string(),
+            muted: "dimgrey".to_string(),
+            grid: "grey".to_string(),
+            climbs: "orange".to_string(),
+            efforts: ["orange", "crimson", "teal", "goldenrod", "seagreen"].map(String::from).to_vec(),
+            grades: ["#2c7bb6", "#6baed6", "#abd9e9", "#b2abd2", "#fdae61", "#f46d43", "#d7191c"].map(String::from),
+        }
+    }
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Font { family: "sans-serif".to_string(), size: 16.0 }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            orientation: Orientation::Portrait,
+            width: 300.0,
+            padding: 10.0,
+            map_height: 300.0,
+            panels: vec!(Panel::Route, Panel::Elevation, Panel::Title, Panel::Stats, Panel::Splits),
+            colors: Colors::default(),
+            font: Font::default(),
+        }
+    }
+}
+
+impl Theme {
+    /// Названия встроенных тем
+    pub fn presets() -> Vec<&'static str> {
+        let mut names: Vec<&'static str> = PRESETS.keys().copied().collect();
+        names.sort();
+
+        names
+    }
+
+    /// Тема из текста в формате TOML
+    pub fn from_toml(text: &str) -> Result<Theme, Error> {
+        let theme: Theme = toml::from_str(text).map_err(|err| Error::InvalidTheme(err.message().to_string()))?;
+        if theme.width <= 0.0 || theme.map_height <= 0.0 || theme.padding < 0.0 {
+            return Err(Error::InvalidTheme("width, map_height, padding".to_string()));
+        }
+
+        Ok(theme)
+    }
+
+    /// Встроенная тема по названию либо тема из TOML-файла по пути
+    pub fn load(name: &str) -> Result<Theme, Error> {
+        if let Some(text) = PRESETS.get(name) {
+            return Theme::from_toml(text);
+        }
+        if !Path::new(name).is_file() {
+            return Err(Error::UnknownTheme(name.to_string()));
+        }
+
+        Theme::from_toml(&fs::read_to_string(name)?)
+    }
+}
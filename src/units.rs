@@ -0,0 +1,134 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::Error;
+
+
+/// Система единиц измерения вывода. Статистика всегда считается
+/// в метрах, пересчет выполняется только при выводе
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Units {
+    #[default]
+    Metric, // Километры, метры, км/ч
+    Imperial, // Мили, футы, мили/ч
+    Nautical, // Морские мили, метры, узлы
+}
+
+impl Units {
+    /// Длина единицы расстояния в метрах
+    pub fn distance_unit(&self) -> f64 {
+        match self {
+            Units::Metric => 1000.0,
+            Units::Imperial => 1609.344,
+            Units::Nautical => 1852.0,
+        }
+    }
+
+    /// Длина единицы высоты в метрах
+    pub fn elevation_unit(&self) -> f64 {
+        match self {
+            Units::Metric | Units::Nautical => 1.0,
+            Units::Imperial => 0.3048,
+        }
+    }
+
+    /// Расстояние в метрах в единицах расстояния
+    pub fn distance(&self, meters: f64) -> f64 {
+        meters / self.distance_unit()
+    }
+
+    /// Высота в метрах в единицах высоты
+    pub fn elevation(&self, meters: f64) -> f64 {
+        meters / self.elevation_unit()
+    }
+
+    /// Скорость в метрах/час в единицах расстояния в час
+    pub fn speed(&self, speed: f64) -> f64 {
+        self.distance(speed)
+    }
+
+    /// Темп - секунд на единицу расстояния при скорости в метрах/час
+    pub fn pace(&self, speed: f64) -> Option<f64> {
+        (speed > 0.0).then(|| 3600.0 / self.speed(speed))
+    }
+
+    // Индекс в массивах названий единиц каталога сообщений
+    pub(crate) fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl fmt::Display for Units {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Units::Metric => write!(f, "metric"),
+            Units::Imperial => write!(f, "imperial"),
+            Units::Nautical => write!(f, "nautical"),
+        }
+    }
+}
+
+impl FromStr for Units {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "metric" | "si" => Ok(Units::Metric),
+            "imperial" | "us" => Ok(Units::Imperial),
+            "nautical" => Ok(Units::Nautical),
+            _ => Err(Error::UnknownUnits(s.to_string())),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn conversions() {
+        assert!(close(Units::Metric.distance(42195.0), 42.195));
+        assert!(close(Units::Imperial.distance(1609.344), 1.0));
+        assert!(close(Units::Nautical.distance(1852.0), 1.0));
+
+        assert!(close(Units::Metric.elevation(100.0), 100.0));
+        assert!(close(Units::Imperial.elevation(304.8), 1000.0));
+        assert!(close(Units::Nautical.elevation(100.0), 100.0));
+
+        // 18.52 км/ч - ровно 10 узлов
+        assert!(close(Units::Metric.speed(18520.0), 18.52));
+        assert!(close(Units::Nautical.speed(18520.0), 10.0));
+        assert!(close(Units::Imperial.speed(16093.44), 10.0));
+    }
+
+    #[test]
+    fn paces() {
+        // 12 км/ч - 5 минут на километр, 8:02.7 на милю
+        assert!(close(Units::Metric.pace(12000.0).unwrap(), 300.0));
+        assert!(close(Units::Imperial.pace(12000.0).unwrap(), 482.8032));
+        assert!(close(Units::Imperial.pace(16093.44).unwrap(), 360.0));
+        assert!(close(Units::Nautical.pace(18520.0).unwrap(), 360.0));
+    }
+
+    #[test]
+    fn no_pace_without_speed() {
+        assert_eq!(Units::Metric.pace(0.0), None);
+        assert_eq!(Units::Imperial.pace(0.0), None);
+        assert_eq!(Units::Metric.pace(-1.0), None);
+    }
+
+    #[test]
+    fn names() {
+        for units in [Units::Metric, Units::Imperial, Units::Nautical] {
+            assert_eq!(units.to_string().parse::<Units>().unwrap(), units);
+        }
+        assert_eq!(" SI ".parse::<Units>().unwrap(), Units::Metric);
+        assert_eq!("us".parse::<Units>().unwrap(), Units::Imperial);
+        assert!(matches!("parsecs".parse::<Units>(), Err(Error::UnknownUnits(_))));
+    }
+}